@@ -2,8 +2,9 @@ use anyhow::{Result, Context};
 use tracing::{info, error, warn};
 use wc_config::loader::load_config;
 use wc_platform::{LinuxInputInjector, InputInjector, MouseButton, ScrollAxis, KeyCode};
-use wc_transport::protocol::Packet;
+use wc_protocol::{framing, BinaryPacket, ControlResponse, InputEvent, MediaMetadata};
 use wc_processing::AudioAnalyzer;
+use wc_core::scroll::ScrollAccumulator;
 use tokio::signal;
 use tokio::sync::{mpsc, broadcast};
 
@@ -53,7 +54,7 @@ async fn main() -> Result<()> {
     
     // Create communication channels
     let (tx, mut rx) = mpsc::channel(100);
-    let (broadcast_tx, _) = broadcast::channel::<Packet>(1024); // Large buffer for video frames
+    let (broadcast_tx, _) = broadcast::channel::<Vec<u8>>(1024); // Large buffer for video frames
     
     // Start TCP Server (Primary for current Android Client)
     let tcp_server = wc_transport::tcp::TcpServer::new(bind_addr, tx, broadcast_tx.clone());
@@ -67,13 +68,16 @@ async fn main() -> Result<()> {
     let mut input_injector = _input;
     let b_tx_ctrl = broadcast_tx.clone();
     tokio::spawn(async move {
-        while let Some(packet) = rx.recv().await {
+        // The injector only has whole notches; fractions carry over
+        let mut scroll_x = ScrollAccumulator::default();
+        let mut scroll_y = ScrollAccumulator::default();
+        while let Some(event) = rx.recv().await {
             if let Some(ref mut inj) = input_injector {
-                match packet {
-                    Packet::MouseMove { dx, dy } => {
+                match event {
+                    InputEvent::Move { dx, dy } => {
                         let _ = inj.move_mouse(dx as i32, dy as i32).await;
                     },
-                    Packet::MouseClick { button, state: _ } => {
+                    InputEvent::Click { button } => {
                          let btn = match button.as_str() {
                              "right" => MouseButton::Right,
                              "middle" => MouseButton::Middle,
@@ -81,10 +85,17 @@ async fn main() -> Result<()> {
                          };
                          let _ = inj.click(btn).await;
                     },
-                    Packet::MouseScroll { axis: _, amount } => {
-                        let _ = inj.scroll(ScrollAxis::Vertical, amount as i32).await;
+                    InputEvent::Scroll { dx, dy, precise } => {
+                        let vertical = scroll_y.feed(dy, precise);
+                        let horizontal = scroll_x.feed(dx, precise);
+                        if vertical.notches != 0 {
+                            let _ = inj.scroll(ScrollAxis::Vertical, vertical.notches).await;
+                        }
+                        if horizontal.notches != 0 {
+                            let _ = inj.scroll(ScrollAxis::Horizontal, horizontal.notches).await;
+                        }
                     },
-                    Packet::KeyPress { key } => {
+                    InputEvent::KeyPress { key } => {
                         let key_code = match key.as_str() {
                             "Escape" => KeyCode::Escape,
                             "Tab" => KeyCode::Tab,
@@ -99,32 +110,39 @@ async fn main() -> Result<()> {
                         };
                         let _ = inj.key_press(key_code).await;
                     },
-                    Packet::Handshake { version, device_name } => {
+                    InputEvent::PairRequest { device_name, version, .. } => {
                         info!("🤝 Handshake from {}: v{}", device_name, version);
-                        // Send back ControlResponse (Success / Trusted)
-                        let _ = b_tx_ctrl.send(Packet::ControlResponse {
-                            status: "success".to_string(),
-                            message: "Trusted".to_string(),
-                            data: Some(serde_json::json!({
-                                "server_name": "WaylandConnect Linux"
-                            })),
-                        });
+                        // Send back PairResponse (Trusted)
+                        let response = ControlResponse::PairResponse {
+                            status: "Trusted".to_string(),
+                            server_version: env!("CARGO_PKG_VERSION").to_string(),
+                            server_name: "WaylandConnect Linux".to_string(),
+                            fingerprint: None,
+                            capabilities: Vec::new(),
+                            protocol_version: wc_protocol::PROTOCOL_VERSION,
+                        };
+                        broadcast(&b_tx_ctrl, &response);
                     },
-                    Packet::ControlRequest { action, params: _ } => {
-                        if action == "media_get_status" {
-                             // This is a bit complex because we need to return the status.
-                             // For now, we broadcast it to everyone as an update.
-                             // In a real app, we might want unicast, but broadcast works for simple discovery.
-                             // We'll placeholder the metadata here or fetch from a shared state.
-                             let dummy_metadata = serde_json::json!({
-                                 "title": "WaylandConnect Audio",
-                                 "artist": "System Broadcast",
-                                 "status": "Playing",
-                                 "position": 0,
-                                 "duration": 0,
-                             });
-                             let _ = b_tx_ctrl.send(Packet::MediaMetadata { metadata: dummy_metadata });
-                        }
+                    InputEvent::MediaGetStatus => {
+                         // This is a bit complex because we need to return the status.
+                         // For now, we broadcast it to everyone as an update.
+                         // In a real app, we might want unicast, but broadcast works for simple discovery.
+                         // We'll placeholder the metadata here or fetch from a shared state.
+                         let dummy_metadata = MediaMetadata {
+                             title: "WaylandConnect Audio".to_string(),
+                             artist: "System Broadcast".to_string(),
+                             album: String::new(),
+                             art_url: String::new(),
+                             duration: 0,
+                             position: 0,
+                             status: "Playing".to_string(),
+                             player_name: String::new(),
+                             shuffle: false,
+                             repeat: "None".to_string(),
+                             volume: 1.0,
+                             track_id: String::new(),
+                         };
+                         broadcast(&b_tx_ctrl, &ControlResponse::MediaStatus { metadata: Some(dummy_metadata) });
                     },
                     _ => {}
                 }
@@ -147,7 +165,7 @@ async fn main() -> Result<()> {
                  let low = mags.iter().take(10).sum::<f32>() / 10.0;
                  let mid = mags.iter().skip(10).take(50).sum::<f32>() / 50.0;
                  let high = mags.iter().skip(60).take(100).sum::<f32>() / 100.0;
-                 let _ = b_tx_audio.send(Packet::Spectrum { low, mid, high });
+                 broadcast(&b_tx_audio, &BinaryPacket::Spectrum { bands: vec![low, mid, high] });
             }
         }
     });
@@ -181,12 +199,7 @@ async fn main() -> Result<()> {
                              frame_count += 1;
                              
                              // Send to Clients
-                             let packet = Packet::VideoFrame {
-                                 timestamp: frame_count,
-                                 keyframe: true,
-                                 data: encoded.to_vec(),
-                             };
-                             let _ = b_tx_video.send(packet);
+                             broadcast(&b_tx_video, &BinaryPacket::Frame { b: encoded.to_vec() });
 
                              if frame_count % 60 == 0 {
                                  info!("⚡ Encoded & Broadcasted frame {}", frame_count);
@@ -220,3 +233,13 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Frames `message` once and hands it to every connected client.
+fn broadcast<T: serde::Serialize>(tx: &broadcast::Sender<Vec<u8>>, message: &T) {
+    match framing::encode(message) {
+        Ok(frame) => {
+            let _ = tx.send(frame);
+        }
+        Err(e) => error!("Failed to encode message: {}", e),
+    }
+}
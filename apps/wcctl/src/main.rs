@@ -0,0 +1,230 @@
+//! `wcctl`: command-line client for the Wayland Connect backend.
+//!
+//! Connects to the local control socket as a dashboard, so it can do
+//! everything the desktop app does. `--json` prints machine-readable output.
+
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use wc_client::Client;
+use wc_protocol::{ControlResponse, InputEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Parser)]
+#[command(name = "wcctl", version, about = "Control the Wayland Connect backend")]
+struct Cli {
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+    /// Control socket (default: $XDG_RUNTIME_DIR/wayland-connect.sock)
+    #[arg(long, global = true)]
+    socket: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Paired devices
+    #[command(subcommand)]
+    Devices(DevicesCommand),
+    /// Screen mirroring
+    #[command(subcommand)]
+    Mirror(MirrorCommand),
+    /// Pointer overlay
+    #[command(subcommand)]
+    Pointer(PointerCommand),
+    /// Media player
+    #[command(subcommand)]
+    Media(MediaCommand),
+    /// Installed applications
+    #[command(subcommand)]
+    Apps(AppsCommand),
+    /// List monitors
+    Monitors,
+    /// Server settings
+    #[command(subcommand)]
+    Settings(SettingsCommand),
+    /// Server certificate
+    #[command(subcommand)]
+    Certificate(CertificateCommand),
+    /// Security audit log, newest first
+    Audit {
+        /// Only entries older than this `seq` (for the next page)
+        #[arg(long)]
+        before: Option<u64>,
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum DevicesCommand {
+    List,
+    Approve { id: String },
+    Reject { id: String },
+    Block { id: String },
+    Unblock { id: String },
+}
+
+#[derive(Subcommand)]
+enum MirrorCommand {
+    /// Stop mirroring (the current device if no id is given)
+    Stop { id: Option<String> },
+    /// Answer a pending mirroring request
+    Accept { id: String },
+    Decline { id: String },
+}
+
+#[derive(Subcommand)]
+enum PointerCommand {
+    /// Run the overlay test sequence
+    Test,
+}
+
+#[derive(Subcommand)]
+enum MediaCommand {
+    Status,
+}
+
+#[derive(Subcommand)]
+enum AppsCommand {
+    List,
+}
+
+#[derive(Subcommand)]
+enum CertificateCommand {
+    /// Generate a new key pair; the current one stays in use for the grace period
+    Rotate {
+        #[arg(long, default_value_t = 86400)]
+        grace_secs: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum SettingsCommand {
+    AutoConnect { #[arg(action = clap::ArgAction::Set)] enabled: bool },
+    Zoom { #[arg(action = clap::ArgAction::Set)] enabled: bool },
+    RequirePairingCode { #[arg(action = clap::ArgAction::Set)] enabled: bool },
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let cli = Cli::parse();
+    let json = cli.json;
+    let result = tokio::time::timeout(TIMEOUT, run(cli)).await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out waiting for the backend")));
+    match result {
+        Ok(output) => print(&output, json),
+        Err(e) => {
+            if json {
+                println!("{}", json!({ "ok": false, "error": format!("{:#}", e) }));
+            } else {
+                eprintln!("wcctl: {:#}", e);
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<Value> {
+    let client = connect(cli.socket).await?;
+
+    match cli.command {
+        Command::Devices(DevicesCommand::List) => Ok(serde_json::to_value(client.status().await?)?),
+        Command::Devices(DevicesCommand::Approve { id }) => ack(&client, InputEvent::ApproveDevice { id }).await,
+        Command::Devices(DevicesCommand::Reject { id }) => ack(&client, InputEvent::RejectDevice { id }).await,
+        Command::Devices(DevicesCommand::Block { id }) => ack(&client, InputEvent::BlockDevice { id }).await,
+        Command::Devices(DevicesCommand::Unblock { id }) => ack(&client, InputEvent::UnblockDevice { id }).await,
+        Command::Mirror(MirrorCommand::Stop { id }) => {
+            let id = match id {
+                Some(id) => id,
+                None => {
+                    match client.status().await?.into_iter().find(|d| d.is_mirroring) {
+                        Some(device) => device.id,
+                        None => bail!("no device is mirroring"),
+                    }
+                }
+            };
+            ack(&client, InputEvent::PCStopMirroring { id }).await
+        }
+        Command::Mirror(MirrorCommand::Accept { id }) => ack(&client, InputEvent::MirrorResponse { device_id: id, accepted: true }).await,
+        Command::Mirror(MirrorCommand::Decline { id }) => ack(&client, InputEvent::MirrorResponse { device_id: id, accepted: false }).await,
+        Command::Pointer(PointerCommand::Test) => ack(&client, InputEvent::TestOverlaySequence).await,
+        Command::Media(MediaCommand::Status) => Ok(serde_json::to_value(client.media_status().await?)?),
+        Command::Apps(AppsCommand::List) => Ok(serde_json::to_value(client.apps().await?)?),
+        Command::Monitors => Ok(serde_json::to_value(client.monitors().await?)?),
+        Command::Settings(SettingsCommand::AutoConnect { enabled }) => ack(&client, InputEvent::SetAutoConnect { enabled }).await,
+        Command::Settings(SettingsCommand::Zoom { enabled }) => ack(&client, InputEvent::SetZoomEnabled { enabled }).await,
+        Command::Settings(SettingsCommand::RequirePairingCode { enabled }) => ack(&client, InputEvent::SetRequirePairingCode { enabled }).await,
+        Command::Certificate(CertificateCommand::Rotate { grace_secs }) => {
+            match client.rotate_certificate(grace_secs).await? {
+                ControlResponse::CertificateRotation { old_fingerprint, new_fingerprint, switch_at_ms, .. } => {
+                    Ok(json!({ "old_fingerprint": old_fingerprint, "new_fingerprint": new_fingerprint, "switch_at_ms": switch_at_ms }))
+                }
+                other => bail!("unexpected response {:?}", other),
+            }
+        }
+        Command::Audit { before, limit } => Ok(serde_json::to_value(client.audit_log(before, limit).await?.0)?),
+    }
+}
+
+/// Connects to the control socket and registers as a dashboard.
+async fn connect(socket: Option<PathBuf>) -> Result<Client> {
+    let path = match socket {
+        Some(path) => path,
+        None => {
+            let dir = std::env::var_os("XDG_RUNTIME_DIR").context("XDG_RUNTIME_DIR is not set; pass --socket")?;
+            PathBuf::from(dir).join(wc_core::constants::CONTROL_SOCKET_NAME)
+        }
+    };
+    let client = Client::connect_local(&path).await
+        .with_context(|| format!("cannot connect to {:?}; is the backend running?", path))?;
+
+    let mut token_path = dirs::config_dir().context("no config directory")?;
+    token_path.push(wc_core::constants::CONFIG_DIR_NAME);
+    token_path.push(wc_core::constants::DASHBOARD_TOKEN_FILE);
+    let token = std::fs::read_to_string(&token_path).with_context(|| format!("cannot read {:?}", token_path))?;
+    client.register_dashboard(token.trim()).await.context("the backend refused the dashboard registration")?;
+    Ok(client)
+}
+
+/// For commands whose only answer is the `Ack`.
+async fn ack(client: &Client, event: InputEvent) -> Result<Value> {
+    client.request(event).await?;
+    Ok(json!({ "ok": true }))
+}
+
+fn print(output: &Value, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(output).unwrap_or_default());
+        return;
+    }
+    match output {
+        Value::Array(items) if items.is_empty() => println!("(none)"),
+        Value::Array(items) => {
+            for item in items {
+                println!("{}", summary(item));
+            }
+        }
+        Value::Null => println!("(none)"),
+        Value::Object(map) if map.get("ok") == Some(&Value::Bool(true)) && map.len() == 1 => println!("ok"),
+        other => println!("{}", summary(other)),
+    }
+}
+
+/// One line per item, with its identifying fields.
+fn summary(item: &Value) -> String {
+    let Value::Object(map) = item else { return item.to_string() };
+    const KEYS: &[&str] = &["seq", "action", "id", "device_id", "name", "status", "ip", "outcome", "detail", "old_fingerprint", "new_fingerprint", "switch_at_ms", "title", "artist", "player_name", "exec", "width", "height"];
+    KEYS.iter()
+        .filter_map(|k| map.get(*k).map(|v| match v {
+            Value::String(s) => format!("{}={}", k, s),
+            other => format!("{}={}", k, other),
+        }))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
@@ -0,0 +1,439 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_rustls::TlsConnector;
+use wc_protocol::{
+    framing, AppInfo, AuditEntry, BinaryPacket, ClientMessage, ControlResponse, DeviceInfo, InputEvent, MediaMetadata, MonitorInfo, TouchContact,
+};
+use crate::error::ClientError;
+use crate::tls::{self, Identity};
+
+/// Events buffered for `take_events` before new ones are dropped.
+const EVENT_BUFFER: usize = 256;
+
+/// Everything the server sends, in order, including the answers to requests.
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    Control(ControlResponse),
+    Binary(BinaryPacket),
+}
+
+/// Parameters of a `PairRequest`.
+#[derive(Debug, Clone)]
+pub struct Pairing {
+    pub device_name: String,
+    pub device_id: String,
+    pub version: String,
+    pub auto_reconnect: Option<bool>,
+    pub pairing_proof: Option<String>,
+    /// `None` gets the legacy feature set
+    pub capabilities: Option<Vec<String>>,
+}
+
+impl Pairing {
+    pub fn new(device_name: impl Into<String>, device_id: impl Into<String>) -> Self {
+        Self {
+            device_name: device_name.into(),
+            device_id: device_id.into(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            auto_reconnect: None,
+            pairing_proof: None,
+            capabilities: None,
+        }
+    }
+
+    pub fn capabilities(mut self, capabilities: &[&str]) -> Self {
+        self.capabilities = Some(capabilities.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    /// Proves the code shown by the dashboard, so the device is trusted
+    /// without a manual approval.
+    pub fn with_code(mut self, code: &str, server_fingerprint: &str) -> Self {
+        let mut mac = Hmac::<Sha256>::new_from_slice(code.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(self.device_id.as_bytes());
+        mac.update(server_fingerprint.as_bytes());
+        self.pairing_proof = Some(mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect());
+        self
+    }
+}
+
+/// The server's `PairResponse`.
+#[derive(Debug, Clone)]
+pub struct Paired {
+    /// "Trusted", "Pending", "Blocked", "CodeRequired", ...
+    pub status: String,
+    pub server_name: String,
+    pub server_version: String,
+    pub capabilities: Vec<String>,
+    pub protocol_version: u32,
+}
+
+struct PendingRequest {
+    id: u64,
+    responses: Vec<ControlResponse>,
+    done: oneshot::Sender<Result<Vec<ControlResponse>, ClientError>>,
+}
+
+type Pending = Arc<StdMutex<VecDeque<PendingRequest>>>;
+type Writer = WriteHalf<Box<dyn Stream>>;
+
+trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Stream for T {}
+
+/// A connection to the server.
+///
+/// Requests carry a `request_id` and resolve on the matching `Ack`. The
+/// server handles a connection's messages in order, so responses that
+/// arrive before that `Ack` are returned with it.
+pub struct Client {
+    writer: Mutex<Writer>,
+    pending: Pending,
+    next_request_id: AtomicU64,
+    events: StdMutex<Option<mpsc::Receiver<ServerEvent>>>,
+    server_fingerprint: Option<String>,
+    /// DER of the certificate the server presented (TLS only)
+    server_certificate: Option<Vec<u8>>,
+}
+
+impl Client {
+    /// Connects over TLS. With `pinned_fingerprint` set, any other server
+    /// certificate fails the handshake; without it the certificate is
+    /// accepted and `server_fingerprint` tells what to pin next time.
+    pub async fn connect(addr: &str, pinned_fingerprint: Option<&str>) -> Result<Self, ClientError> {
+        Self::connect_tls(addr, pinned_fingerprint, None).await
+    }
+
+    /// Like `connect`, presenting the certificate issued at pairing.
+    pub async fn connect_with_identity(addr: &str, pinned_fingerprint: Option<&str>, identity: &Identity) -> Result<Self, ClientError> {
+        Self::connect_tls(addr, pinned_fingerprint, Some(identity)).await
+    }
+
+    /// Connects to the local control socket. Call `register_dashboard`
+    /// before sending management requests.
+    pub async fn connect_local(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        Ok(Self::from_stream(Box::new(stream), None))
+    }
+
+    async fn connect_tls(addr: &str, pinned: Option<&str>, identity: Option<&Identity>) -> Result<Self, ClientError> {
+        let verifier = tls::verifier(pinned);
+        let connector = TlsConnector::from(Arc::new(tls::client_config(verifier.clone(), identity)?));
+        let tcp = TcpStream::connect(addr).await?;
+        tcp.set_nodelay(true)?;
+
+        // The server certificate is only checked by fingerprint
+        let server_name = rustls::ServerName::try_from("localhost").expect("valid server name");
+        let stream = match connector.connect(server_name, tcp).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                let presented = verifier.presented.lock().unwrap().as_deref().map(tls::certificate_fingerprint);
+                return Err(match (pinned, presented) {
+                    (Some(pinned), Some(presented)) if !pinned.eq_ignore_ascii_case(&presented) => {
+                        ClientError::FingerprintMismatch { pinned: pinned.to_string(), presented }
+                    }
+                    _ => e.into(),
+                });
+            }
+        };
+        let certificate = verifier.presented.lock().unwrap().clone();
+        Ok(Self::from_stream(Box::new(stream), certificate))
+    }
+
+    fn from_stream(stream: Box<dyn Stream>, server_certificate: Option<Vec<u8>>) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        let pending: Pending = Arc::new(StdMutex::new(VecDeque::new()));
+        let (events_tx, events_rx) = mpsc::channel(EVENT_BUFFER);
+        tokio::spawn(read_loop(reader, pending.clone(), events_tx));
+
+        Self {
+            writer: Mutex::new(writer),
+            pending,
+            next_request_id: AtomicU64::new(1),
+            events: StdMutex::new(Some(events_rx)),
+            server_fingerprint: server_certificate.as_deref().map(tls::certificate_fingerprint),
+            server_certificate,
+        }
+    }
+
+    /// Fingerprint of the certificate the server presented (TLS only).
+    pub fn server_fingerprint(&self) -> Option<&str> {
+        self.server_fingerprint.as_deref()
+    }
+
+    /// Checks a `CertificateRotation` against the certificate this connection
+    /// was made with and returns the fingerprint to pin from its
+    /// `switch_at_ms` on. Anything not signed by the pinned key is an error.
+    pub fn verify_rotation(&self, rotation: &ControlResponse) -> Result<String, ClientError> {
+        let ControlResponse::CertificateRotation { old_fingerprint, new_fingerprint, switch_at_ms, scheme, signature } = rotation else {
+            return Err(ClientError::UnexpectedResponse(format!("{:?}", rotation)));
+        };
+        let (Some(certificate), Some(current)) = (&self.server_certificate, &self.server_fingerprint) else {
+            return Err(ClientError::InvalidRotation("not a TLS connection".to_string()));
+        };
+        if !old_fingerprint.eq_ignore_ascii_case(current) {
+            return Err(ClientError::InvalidRotation(format!("announced for {}, connected to {}", old_fingerprint, current)));
+        }
+        let statement = wc_protocol::rotation_statement(old_fingerprint, new_fingerprint, *switch_at_ms);
+        tls::verify_signature(certificate, scheme, &statement, signature)?;
+        Ok(new_fingerprint.clone())
+    }
+
+    /// The stream of everything the server sends. Can be taken once; events
+    /// are dropped while nobody reads them and the buffer is full.
+    pub fn take_events(&self) -> Option<mpsc::Receiver<ServerEvent>> {
+        self.events.lock().unwrap().take()
+    }
+
+    /// Sends `event` without waiting for an answer, for high-rate input
+    /// such as pointer movement.
+    pub async fn send(&self, event: InputEvent) -> Result<(), ClientError> {
+        self.write(&ClientMessage { event, request_id: None }).await
+    }
+
+    /// Sends `event` and waits for its `Ack`; returns the responses that
+    /// came before it.
+    pub async fn request(&self, event: InputEvent) -> Result<Vec<ControlResponse>, ClientError> {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (done, rx) = oneshot::channel();
+
+        // Hold the writer while queueing so queue order is wire order
+        let mut writer = self.writer.lock().await;
+        self.pending.lock().unwrap().push_back(PendingRequest { id, responses: Vec::new(), done });
+        let frame = framing::encode(&ClientMessage { event, request_id: Some(id) })?;
+        if let Err(e) = write_frame(&mut writer, &frame).await {
+            self.pending.lock().unwrap().retain(|p| p.id != id);
+            return Err(e);
+        }
+        drop(writer);
+
+        rx.await.unwrap_or(Err(ClientError::Closed))
+    }
+
+    /// Ends the connection from this side; the server handles it as a
+    /// disconnect.
+    pub async fn close(&self) -> Result<(), ClientError> {
+        self.writer.lock().await.shutdown().await?;
+        Ok(())
+    }
+
+    async fn write(&self, message: &ClientMessage) -> Result<(), ClientError> {
+        let frame = framing::encode(message)?;
+        write_frame(&mut *self.writer.lock().await, &frame).await
+    }
+
+    pub async fn pair(&self, pairing: Pairing) -> Result<Paired, ClientError> {
+        let responses = self.request(InputEvent::PairRequest {
+            device_name: pairing.device_name,
+            id: pairing.device_id,
+            version: pairing.version,
+            auto_reconnect: pairing.auto_reconnect,
+            pairing_proof: pairing.pairing_proof,
+            capabilities: pairing.capabilities,
+        }).await?;
+        find(responses, "pair_response", |r| match r {
+            ControlResponse::PairResponse { status, server_version, server_name, capabilities, protocol_version, .. } => {
+                Some(Paired { status, server_name, server_version, capabilities, protocol_version })
+            }
+            _ => None,
+        })
+    }
+
+    pub async fn register_dashboard(&self, token: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::RegisterDashboard { token: token.to_string() }).await?;
+        Ok(())
+    }
+
+    /// Starts `DashboardEvent`s on the event stream (dashboards only).
+    pub async fn subscribe_events(&self) -> Result<Vec<DeviceInfo>, ClientError> {
+        let responses = self.request(InputEvent::SubscribeEvents).await?;
+        find(responses, "status_response", status_devices)
+    }
+
+    pub async fn status(&self) -> Result<Vec<DeviceInfo>, ClientError> {
+        let responses = self.request(InputEvent::GetStatus).await?;
+        find(responses, "status_response", status_devices)
+    }
+
+    /// A page of the security audit log, newest first, and the `before` for
+    /// the next page (`None` on the last one). Dashboards only.
+    pub async fn audit_log(&self, before: Option<u64>, limit: u32) -> Result<(Vec<AuditEntry>, Option<u64>), ClientError> {
+        let responses = self.request(InputEvent::GetAuditLog { before, limit }).await?;
+        find(responses, "audit_log", |r| match r {
+            ControlResponse::AuditLog { entries, next_before } => Some((entries, next_before)),
+            _ => None,
+        })
+    }
+
+    /// Starts a server certificate rotation that takes effect after
+    /// `grace_secs`; returns the `CertificateRotation` sent to paired devices.
+    /// Dashboards only.
+    pub async fn rotate_certificate(&self, grace_secs: u64) -> Result<ControlResponse, ClientError> {
+        let responses = self.request(InputEvent::RotateCertificate { grace_secs }).await?;
+        find(responses, "certificate_rotation", |r| matches!(r, ControlResponse::CertificateRotation { .. }).then_some(r))
+    }
+
+    pub async fn move_by(&self, dx: f64, dy: f64) -> Result<(), ClientError> {
+        self.request(InputEvent::Move { dx, dy }).await.map(drop)
+    }
+
+    pub async fn click(&self, button: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::Click { button: button.to_string() }).await.map(drop)
+    }
+
+    pub async fn scroll(&self, dy: f64) -> Result<(), ClientError> {
+        self.request(InputEvent::Scroll { dx: 0.0, dy, precise: false }).await.map(drop)
+    }
+
+    /// Sends every finger currently down; an empty slice lifts them all.
+    pub async fn touch_frame(&self, contacts: &[TouchContact]) -> Result<(), ClientError> {
+        self.request(InputEvent::TouchFrame { contacts: contacts.to_vec() }).await.map(drop)
+    }
+
+    /// Touches the mirrored screen at `x`, `y` (0.0-1.0 of the monitor);
+    /// `state` is "down", "move" or "up".
+    pub async fn screen_touch(&self, x: f64, y: f64, state: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::ScreenTouch { x, y, state: state.to_string() }).await.map(drop)
+    }
+
+    /// Scrolls both axes, in notches; `precise` for smooth touchpad-style scrolling.
+    pub async fn scroll_by(&self, dx: f64, dy: f64, precise: bool) -> Result<(), ClientError> {
+        self.request(InputEvent::Scroll { dx, dy, precise }).await.map(drop)
+    }
+
+    pub async fn key_press(&self, key: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::KeyPress { key: key.to_string() }).await.map(drop)
+    }
+
+    pub async fn key_down(&self, key: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::KeyDown { key: key.to_string() }).await.map(drop)
+    }
+
+    pub async fn key_up(&self, key: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::KeyUp { key: key.to_string() }).await.map(drop)
+    }
+
+    /// Enters `text` as is, independent of the desktop's keyboard layout.
+    pub async fn type_text(&self, text: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::TypeText { text: text.to_string() }).await.map(drop)
+    }
+
+    /// E.g. `key_combo(&["ctrl"], "c")`
+    pub async fn key_combo(&self, modifiers: &[&str], key: &str) -> Result<(), ClientError> {
+        let modifiers = modifiers.iter().map(|m| m.to_string()).collect();
+        self.request(InputEvent::KeyCombo { modifiers, key: key.to_string() }).await.map(drop)
+    }
+
+    pub async fn media_control(&self, action: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::MediaControl { action: action.to_string() }).await.map(drop)
+    }
+
+    pub async fn media_status(&self) -> Result<Option<MediaMetadata>, ClientError> {
+        let responses = self.request(InputEvent::MediaGetStatus).await?;
+        find(responses, "media_status", |r| match r {
+            ControlResponse::MediaStatus { metadata } => Some(metadata),
+            _ => None,
+        })
+    }
+
+    pub async fn apps(&self) -> Result<Vec<AppInfo>, ClientError> {
+        let responses = self.request(InputEvent::GetApps).await?;
+        find(responses, "apps_list", |r| match r {
+            ControlResponse::AppsList { apps } => Some(apps),
+            _ => None,
+        })
+    }
+
+    pub async fn launch_app(&self, command: &str) -> Result<(), ClientError> {
+        self.request(InputEvent::LaunchApp { command: command.to_string() }).await.map(drop)
+    }
+
+    pub async fn monitors(&self) -> Result<Vec<MonitorInfo>, ClientError> {
+        let responses = self.request(InputEvent::GetMonitors).await?;
+        find(responses, "monitors_list", |r| match r {
+            ControlResponse::MonitorsList { monitors } => Some(monitors),
+            _ => None,
+        })
+    }
+
+    /// Asks to mirror `monitor`; frames follow on the event stream once the
+    /// desktop user accepts.
+    pub async fn start_mirroring(&self, width: u32, height: u32, fps: u32, monitor: i32) -> Result<(), ClientError> {
+        self.request(InputEvent::StartMirroring { width, height, fps, monitor }).await.map(drop)
+    }
+
+    pub async fn stop_mirroring(&self) -> Result<(), ClientError> {
+        self.request(InputEvent::StopMirroring).await.map(drop)
+    }
+
+    /// Port and token for the QUIC media connection.
+    pub async fn open_media_channel(&self) -> Result<(u16, String), ClientError> {
+        let responses = self.request(InputEvent::OpenMediaChannel).await?;
+        find(responses, "media_channel", |r| match r {
+            ControlResponse::MediaChannel { port, token } => Some((port, token)),
+            _ => None,
+        })
+    }
+}
+
+async fn write_frame(writer: &mut Writer, frame: &[u8]) -> Result<(), ClientError> {
+    writer.write_all(frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+fn status_devices(response: ControlResponse) -> Option<Vec<DeviceInfo>> {
+    match response {
+        ControlResponse::StatusResponse { devices, .. } => Some(devices),
+        _ => None,
+    }
+}
+
+/// The last response `pick` accepts; others may be unsolicited pushes.
+fn find<T>(responses: Vec<ControlResponse>, expected: &str, pick: impl Fn(ControlResponse) -> Option<T>) -> Result<T, ClientError> {
+    responses.into_iter().rev().find_map(pick)
+        .ok_or_else(|| ClientError::UnexpectedResponse(format!("no {} before the ack", expected)))
+}
+
+async fn read_loop(mut reader: ReadHalf<Box<dyn Stream>>, pending: Pending, events: mpsc::Sender<ServerEvent>) {
+    while let Ok(body) = framing::read_frame(&mut reader).await {
+        let event = match framing::decode::<ControlResponse>(&body) {
+            Ok(ControlResponse::Ack { request_id, ok, error_code, message }) => {
+                let request = {
+                    let mut pending = pending.lock().unwrap();
+                    pending.iter().position(|p| p.id == request_id).and_then(|i| pending.remove(i))
+                };
+                if let Some(request) = request {
+                    let result = if ok {
+                        Ok(request.responses)
+                    } else {
+                        Err(ClientError::Rejected { code: error_code, message: message.unwrap_or_default() })
+                    };
+                    let _ = request.done.send(result);
+                }
+                continue;
+            }
+            Ok(response) => {
+                if let Some(request) = pending.lock().unwrap().front_mut() {
+                    request.responses.push(response.clone());
+                }
+                ServerEvent::Control(response)
+            }
+            Err(_) => match framing::decode::<BinaryPacket>(&body) {
+                Ok(packet) => ServerEvent::Binary(packet),
+                Err(_) => continue,
+            },
+        };
+        let _ = events.try_send(event);
+    }
+
+    for request in pending.lock().unwrap().drain(..) {
+        let _ = request.done.send(Err(ClientError::Closed));
+    }
+}
@@ -0,0 +1,22 @@
+use thiserror::Error;
+use wc_protocol::ErrorCode;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("Encoding error: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("Server certificate {presented} does not match the pinned {pinned}")]
+    FingerprintMismatch { pinned: String, presented: String },
+    #[error("Request failed ({code:?}): {message}")]
+    Rejected { code: Option<ErrorCode>, message: String },
+    #[error("Invalid certificate rotation: {0}")]
+    InvalidRotation(String),
+    #[error("Unexpected response: {0}")]
+    UnexpectedResponse(String),
+    #[error("Connection closed")]
+    Closed,
+}
@@ -0,0 +1,29 @@
+//! Async client for the Wayland Connect server.
+//!
+//! Wraps the TLS connection with fingerprint pinning, the length-prefixed
+//! msgpack framing and request/`Ack` matching, on top of the `wc_protocol`
+//! types the server itself uses.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), wc_client::ClientError> {
+//! use wc_client::{Client, Pairing};
+//!
+//! let client = Client::connect("192.168.1.10:12345", Some("AB:CD:...")).await?;
+//! let mut events = client.take_events().unwrap();
+//! let paired = client.pair(Pairing::new("Test bot", "bot-1")).await?;
+//! println!("pairing status: {}", paired.status);
+//! while let Some(event) = events.recv().await {
+//!     println!("{:?}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod error;
+mod tls;
+
+pub use client::{Client, Paired, Pairing, ServerEvent};
+pub use error::ClientError;
+pub use tls::{certificate_fingerprint, Identity};
+pub use wc_protocol;
@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, ServerName};
+use sha2::{Digest, Sha256};
+use crate::error::ClientError;
+
+/// SHA-256 of a DER certificate in the server's notation (`AB:CD:...`).
+pub fn certificate_fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+}
+
+/// Client certificate the server issues when a pairing is approved
+/// (`ControlResponse::ClientCertificate`).
+#[derive(Clone)]
+pub struct Identity {
+    chain: Vec<Certificate>,
+    key: PrivateKey,
+}
+
+impl Identity {
+    pub fn from_pem(cert_pem: &str, key_pem: &str) -> Result<Self, ClientError> {
+        let chain = rustls_pemfile::certs(&mut cert_pem.as_bytes())?
+            .into_iter()
+            .map(Certificate)
+            .collect::<Vec<_>>();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut key_pem.as_bytes())?
+            .into_iter()
+            .next()
+            .map(PrivateKey)
+            .ok_or_else(|| rustls::Error::General("no PKCS#8 key in key_pem".to_string()))?;
+        Ok(Self { chain, key })
+    }
+}
+
+/// Accepts the server certificate whose fingerprint is pinned, or any
+/// certificate when nothing is pinned yet (trust on first use). Either way
+/// the presented certificate is recorded for the caller to pin.
+pub(crate) struct PinnedVerifier {
+    pinned: Option<String>,
+    pub(crate) presented: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = certificate_fingerprint(&end_entity.0);
+        *self.presented.lock().unwrap() = Some(end_entity.0.clone());
+        match &self.pinned {
+            Some(pinned) if !pinned.eq_ignore_ascii_case(&fingerprint) => {
+                Err(rustls::Error::General("server certificate fingerprint mismatch".to_string()))
+            }
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+}
+
+pub(crate) fn client_config(verifier: Arc<PinnedVerifier>, identity: Option<&Identity>) -> Result<ClientConfig, ClientError> {
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier);
+    Ok(match identity {
+        Some(identity) => builder.with_client_auth_cert(identity.chain.clone(), identity.key.clone())?,
+        None => builder.with_no_client_auth(),
+    })
+}
+
+pub(crate) fn verifier(pinned: Option<&str>) -> Arc<PinnedVerifier> {
+    Arc::new(PinnedVerifier {
+        pinned: pinned.map(str::to_string),
+        presented: Arc::new(Mutex::new(None)),
+    })
+}
+
+/// Checks a hex `signature` over `message` against the key of the DER
+/// certificate `cert`. `scheme` names the TLS signature scheme used.
+pub(crate) fn verify_signature(cert: &[u8], scheme: &str, message: &[u8], signature: &str) -> Result<(), ClientError> {
+    let algorithm = match scheme {
+        "ECDSA_NISTP256_SHA256" => &webpki::ECDSA_P256_SHA256,
+        "ECDSA_NISTP384_SHA384" => &webpki::ECDSA_P384_SHA384,
+        "ED25519" => &webpki::ED25519,
+        "RSA_PSS_SHA256" => &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+        other => return Err(ClientError::InvalidRotation(format!("unsupported signature scheme {}", other))),
+    };
+    let signature = decode_hex(signature)
+        .ok_or_else(|| ClientError::InvalidRotation("signature is not hex".to_string()))?;
+    let cert = webpki::EndEntityCert::try_from(cert)
+        .map_err(|e| ClientError::InvalidRotation(format!("unreadable server certificate: {:?}", e)))?;
+    cert.verify_signature(algorithm, message, &signature)
+        .map_err(|_| ClientError::InvalidRotation("bad signature".to_string()))
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
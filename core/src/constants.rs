@@ -2,7 +2,11 @@ pub const POINTER_OVERLAY_ADDR: &str = "127.0.0.1:7878";
 pub const DISCOVERY_PORT: u16 = 12346;
 pub const DISCOVERY_ADDR: &str = "0.0.0.0:12346";
 pub const DEFAULT_SERVER_PORT: u16 = 12345;
+pub const MEDIA_PORT: u16 = 12347;
+pub const MEDIA_ADDR: &str = "0.0.0.0:12347";
 pub const UINPUT_PATH: &str = "/dev/uinput";
 pub const MOUSE_SENSITIVITY: f64 = 1.6;
 pub const CONFIG_DIR_NAME: &str = "wayland-connect";
 pub const POINTER_IMAGE_PATH: &str = "/tmp/wayland_connect_pointer.png";
+pub const CONTROL_SOCKET_NAME: &str = "wayland-connect.sock";
+pub const DASHBOARD_TOKEN_FILE: &str = "dashboard.token";
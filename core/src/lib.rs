@@ -3,3 +3,4 @@ pub mod error;
 pub mod traits;
 pub mod types;
 pub mod constants;
+pub mod scroll;
@@ -0,0 +1,46 @@
+//! Turns fractional scroll deltas into wheel events the way high-resolution
+//! mice report them: 120ths of a notch on the `*_HI_RES` axes, plus a whole
+//! notch on the legacy axis each time those add up to one.
+
+/// Hi-res units per wheel notch, as the kernel defines them.
+pub const HI_RES_PER_NOTCH: i32 = 120;
+/// Larger deltas are clamped; no phone gesture scrolls this far at once.
+const MAX_NOTCHES_PER_EVENT: f64 = 1000.0;
+
+/// What one delta on one axis comes out as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WheelSteps {
+    /// For `REL_WHEEL_HI_RES` / `REL_HWHEEL_HI_RES`
+    pub hi_res: i32,
+    /// For `REL_WHEEL` / `REL_HWHEEL`
+    pub notches: i32,
+}
+
+/// Remainders of one scroll axis, carried from event to event so slow
+/// scrolling adds up instead of being truncated away.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrollAccumulator {
+    /// Hi-res units received but not sent yet
+    pending: f64,
+    /// Hi-res units sent since the last whole notch
+    partial_notch: i32,
+}
+
+impl ScrollAccumulator {
+    /// `delta` is in notches. Precise deltas come out in 120ths of a notch as
+    /// soon as they add up to one; the others only as whole notches.
+    pub fn feed(&mut self, delta: f64, precise: bool) -> WheelSteps {
+        if !delta.is_finite() {
+            return WheelSteps::default();
+        }
+        self.pending += delta.clamp(-MAX_NOTCHES_PER_EVENT, MAX_NOTCHES_PER_EVENT) * HI_RES_PER_NOTCH as f64;
+        let step = if precise { 1 } else { HI_RES_PER_NOTCH };
+        let hi_res = (self.pending / step as f64).trunc() as i32 * step;
+        self.pending -= hi_res as f64;
+
+        self.partial_notch += hi_res;
+        let notches = self.partial_notch / HI_RES_PER_NOTCH;
+        self.partial_notch -= notches * HI_RES_PER_NOTCH;
+        WheelSteps { hi_res, notches }
+    }
+}
@@ -0,0 +1,28 @@
+use wc_core::scroll::{ScrollAccumulator, WheelSteps};
+
+fn steps(hi_res: i32, notches: i32) -> WheelSteps {
+    WheelSteps { hi_res, notches }
+}
+
+#[test]
+fn precise_deltas_carry_their_remainders() {
+    let mut axis = ScrollAccumulator::default();
+    assert_eq!(axis.feed(0.004, true), steps(0, 0));
+    assert_eq!(axis.feed(0.004, true), steps(0, 0));
+    // 0.012 notches so far: one hi-res unit, 0.44 of one left over
+    assert_eq!(axis.feed(0.004, true), steps(1, 0));
+    assert_eq!(axis.feed(0.5, true), steps(60, 0));
+    assert_eq!(axis.feed(0.5, true), steps(60, 1));
+    // The 0.44 still pending counts against a change of direction
+    assert_eq!(axis.feed(-0.25, true), steps(-29, 0));
+}
+
+#[test]
+fn coarse_deltas_come_out_in_whole_notches() {
+    let mut axis = ScrollAccumulator::default();
+    assert_eq!(axis.feed(0.4, false), steps(0, 0));
+    assert_eq!(axis.feed(0.4, false), steps(0, 0));
+    assert_eq!(axis.feed(0.4, false), steps(120, 1));
+    assert_eq!(axis.feed(-3.0, false), steps(-240, -2));
+    assert_eq!(axis.feed(f64::NAN, false), steps(0, 0));
+}
@@ -0,0 +1,13 @@
+//! Decodes a frame body as a `ClientMessage`. Anything that decodes must
+//! encode again, and the encoding must decode.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wc_protocol::{framing, ClientMessage};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(message) = framing::decode::<ClientMessage>(data) else { return };
+    let encoded = framing::encode_body(&message).expect("decoded message re-encodes");
+    framing::decode::<ClientMessage>(&encoded).expect("re-encoded message decodes");
+});
@@ -0,0 +1,33 @@
+//! Feeds a whole QUIC control stream to the transport's frame reader and
+//! checks the token frame stays within the pre-auth limit and no more frames
+//! come out than were sent.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use wc_transport::server::{read_control_frames, MAX_PREAUTH_CONTROL_FRAME};
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Builder::new_current_thread().build().unwrap())
+}
+
+fuzz_target!(|data: &[u8]| {
+    runtime().block_on(async {
+        // Room for every frame a 4-byte-per-frame minimum could produce
+        let (tx, mut rx) = mpsc::channel(data.len() / 4 + 1);
+        read_control_frames(data, tx).await;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = rx.recv().await {
+            frames.push(frame);
+        }
+        assert!(frames.len() <= data.len() / 4);
+        if let Some(token) = frames.first() {
+            assert!(token.len() <= MAX_PREAUTH_CONTROL_FRAME);
+        }
+    });
+});
@@ -0,0 +1,24 @@
+//! What an untrusted peer's bytes go through in `InputServer`'s reader loop:
+//! frames under the pre-auth limit, each decoded as a `ClientMessage`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+use wc_protocol::{framing, ClientMessage};
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Builder::new_current_thread().build().unwrap())
+}
+
+fuzz_target!(|data: &[u8]| {
+    runtime().block_on(async {
+        let mut input = data;
+        while let Ok(body) = framing::read_frame_limited(&mut input, framing::MAX_PREAUTH_FRAME_LEN).await {
+            assert!(body.len() <= framing::MAX_PREAUTH_FRAME_LEN);
+            let _ = framing::decode::<ClientMessage>(&body);
+        }
+    });
+});
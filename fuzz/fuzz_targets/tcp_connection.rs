@@ -0,0 +1,32 @@
+//! Feeds a whole client stream to the daemon's TCP transport and checks it
+//! ends without panicking and forwards no more events than frames sent.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+use tokio::sync::{broadcast, mpsc};
+use wc_transport::tcp::handle_connection;
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Builder::new_current_thread().build().unwrap())
+}
+
+fuzz_target!(|data: &[u8]| {
+    runtime().block_on(async {
+        // Room for every event a 4-byte-per-frame minimum could produce
+        let (event_tx, mut event_rx) = mpsc::channel(data.len() / 4 + 1);
+        let (broadcast_tx, broadcast_rx) = broadcast::channel(1);
+        let reader = std::io::Cursor::new(data.to_vec());
+        let _ = handle_connection(reader, tokio::io::sink(), event_tx, broadcast_rx).await;
+        drop(broadcast_tx);
+
+        let mut forwarded = 0;
+        while event_rx.recv().await.is_some() {
+            forwarded += 1;
+        }
+        assert!(forwarded <= data.len() / 4);
+    });
+});
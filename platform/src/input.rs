@@ -123,6 +123,7 @@ pub mod linux {
             handle.set_relbit(RelativeAxis::X).map_err(map_err)?;
             handle.set_relbit(RelativeAxis::Y).map_err(map_err)?;
             handle.set_relbit(RelativeAxis::Wheel).map_err(map_err)?;
+            handle.set_relbit(RelativeAxis::HorizontalWheel).map_err(map_err)?;
             
             // Absolute events for Touch/Tablet like control
             // Note: input-linux 0.7 handle doesn't expose ease way to set abs info via ioctl easily without generic access.
@@ -202,7 +203,7 @@ pub mod linux {
         async fn scroll(&mut self, axis: ScrollAxis, distance: i32) -> Result<()> {
              let axis_code = match axis {
                  ScrollAxis::Vertical => RelativeAxis::Wheel,
-                 ScrollAxis::Horizontal => RelativeAxis::Wheel, 
+                 ScrollAxis::Horizontal => RelativeAxis::HorizontalWheel,
              };
              
              self.write_events(&[
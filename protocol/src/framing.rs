@@ -0,0 +1,63 @@
+//! Framing shared by every TCP/TLS connection: a big-endian `u32` length
+//! followed by the message as named msgpack (maps keyed by field name).
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Largest frame either side accepts.
+pub const MAX_FRAME_LEN: usize = 10 * 1024 * 1024;
+
+/// Largest frame a server accepts from a peer that hasn't been trusted yet.
+/// A pairing request with its capability list fits with plenty to spare.
+pub const MAX_PREAUTH_FRAME_LEN: usize = 64 * 1024;
+
+/// Encodes `message` without the length prefix, for transports that keep
+/// message boundaries themselves (UDP, QUIC datagrams).
+pub fn encode_body<T: Serialize>(message: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::encode::to_vec_named(message)
+}
+
+/// Encodes `message` as a complete length-prefixed frame.
+pub fn encode<T: Serialize>(message: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    Ok(frame(&encode_body(message)?))
+}
+
+/// Prefixes an already encoded body with its length.
+pub fn frame(body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(body);
+    frame
+}
+
+pub fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, rmp_serde::decode::Error> {
+    rmp_serde::from_slice(body)
+}
+
+/// Reads one frame body. Frames over `MAX_FRAME_LEN` are an `InvalidData`
+/// error rather than an allocation.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    read_frame_limited(reader, MAX_FRAME_LEN).await
+}
+
+/// Like `read_frame` with a caller-chosen limit. The body buffer grows as
+/// bytes arrive, so a peer claiming a large frame and then stalling costs
+/// nothing up front.
+pub async fn read_frame_limited<R: AsyncRead + Unpin>(reader: &mut R, max_len: usize) -> std::io::Result<Vec<u8>> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf).await?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > max_len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds the limit of {}", len, max_len),
+        ));
+    }
+    let mut body = Vec::with_capacity(len.min(MAX_PREAUTH_FRAME_LEN));
+    reader.take(len as u64).read_to_end(&mut body).await?;
+    if body.len() < len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(body)
+}
@@ -0,0 +1,10 @@
+#![deny(warnings)]
+pub mod framing;
+pub mod messages;
+
+pub use messages::*;
+
+/// Version of the wire format defined here. Additive changes (new variants,
+/// new defaulted fields, new capabilities) keep it; anything that changes
+/// how existing messages encode bumps it.
+pub const PROTOCOL_VERSION: u32 = 1;
@@ -0,0 +1,635 @@
+//! Messages exchanged with the Android app and the desktop dashboard.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// What a client actually sends: an `InputEvent` plus an optional id the
+/// server echoes back in `ControlResponse::Ack`. Clients that don't set it
+/// get the old fire-and-forget behaviour.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClientMessage {
+    #[serde(flatten)]
+    pub event: InputEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", content = "data")]
+pub enum InputEvent {
+    #[serde(rename = "move")]
+    Move { dx: f64, dy: f64 },
+    #[serde(rename = "move_absolute")]
+    MoveAbsolute { x: f64, y: f64 },
+    #[serde(rename = "click")]
+    Click { button: String },
+    #[serde(rename = "mouse_click")]
+    MouseClick { button: String, state: String }, // state: "down" or "up"
+    /// Deltas in wheel notches, positive for up and right. `precise` marks
+    /// continuous (two-finger) scrolling, sent in high-resolution steps;
+    /// otherwise the server scrolls whole notches. Fractions carry over.
+    #[serde(rename = "scroll")]
+    Scroll {
+        #[serde(default)] dx: f64,
+        dy: f64,
+        #[serde(default)] precise: bool,
+    },
+    #[serde(rename = "keypress")]
+    KeyPress { key: String },
+    /// Holds `key` until the matching `KeyUp`, so the desktop repeats it.
+    #[serde(rename = "key_down")]
+    KeyDown { key: String },
+    #[serde(rename = "key_up")]
+    KeyUp { key: String },
+    /// Presses `modifiers` in order, taps `key`, then releases them in reverse
+    /// (e.g. Ctrl+C, Alt+Tab, Super+E).
+    #[serde(rename = "key_combo")]
+    KeyCombo { modifiers: Vec<String>, key: String },
+    /// Enters `text` as written, whatever the desktop's keyboard layout.
+    #[serde(rename = "type_text")]
+    TypeText { text: String },
+    /// Every finger currently on the phone's touchpad area; fingers missing
+    /// from the previous frame were lifted. An empty list lifts them all.
+    #[serde(rename = "touch_frame")]
+    TouchFrame { contacts: Vec<TouchContact> },
+    /// A finger on the mirrored screen, `x` and `y` 0.0-1.0 across the
+    /// mirrored monitor. `state` is "down", "move" or "up"; taps,
+    /// long-presses and drags are just how long and how far the finger goes.
+    #[serde(rename = "screen_touch")]
+    ScreenTouch { x: f64, y: f64, state: String },
+    #[serde(rename = "pair_request")]
+    PairRequest {
+        device_name: String,
+        id: String,
+        #[serde(default)] version: String,
+        #[serde(default)] auto_reconnect: Option<bool>,
+        /// Hex HMAC-SHA256 keyed with the pairing code over `id` + server fingerprint
+        #[serde(default)] pairing_proof: Option<String>,
+        /// Features the client understands; `None` for clients that predate negotiation
+        #[serde(default)] capabilities: Option<Vec<String>>,
+    },
+    
+    // Management Commands
+    #[serde(rename = "get_status")]
+    GetStatus,
+    #[serde(rename = "approve_device")]
+    ApproveDevice { id: String },
+    #[serde(rename = "reject_device")] // Clean up pending
+    RejectDevice { id: String },
+    #[serde(rename = "block_device")]
+    BlockDevice { id: String },
+    #[serde(rename = "test_overlay_sequence")]
+    TestOverlaySequence,
+    #[serde(rename = "unblock_device")] // Alias for approve or remove from block list
+    UnblockDevice { id: String },
+
+    // Media Controls
+    #[serde(rename = "media_control")]
+    MediaControl { action: String }, // "play", "pause", "play_pause", "next", "previous"
+    
+    #[serde(rename = "media_get_status")]
+    MediaGetStatus,
+
+    // Presentation Pointer
+    #[serde(rename = "pointer_data")]
+    PointerData { 
+        active: bool, 
+        mode: i32, 
+        pitch: f32, 
+        roll: f32,
+        #[serde(default = "default_size")] 
+        size: f32,
+        #[serde(default = "default_color")]
+        color: String,
+        #[serde(default = "default_zoom")]
+        zoom_scale: f32,
+        #[serde(default = "default_particle")]
+        particle_type: i32,
+        #[serde(default = "default_stretch")]
+        stretch_factor: f32,
+        #[serde(default)]
+        has_image: bool,
+        #[serde(default = "default_pulse_speed")]
+        pulse_speed: f32,
+        #[serde(default)]
+        pulse_intensity: f32,
+    },
+    
+    // Presentation Slide Control
+    #[serde(rename = "presentation_control")]
+    PresentationControl { action: String }, // "prev" or "next"
+
+    #[serde(rename = "set_pointer_monitor")]
+    SetPointerMonitor { monitor: i32 },
+
+    #[serde(rename = "launch_app")]
+    LaunchApp { command: String },
+
+    #[serde(rename = "get_apps")]
+    GetApps,
+
+    #[serde(rename = "start_mirroring")]
+    StartMirroring { 
+        #[serde(default = "default_width")] width: u32, 
+        #[serde(default = "default_height")] height: u32,
+        #[serde(default = "default_fps")] fps: u32,
+        #[serde(default)] monitor: i32
+    },
+
+    #[serde(rename = "stop_mirroring")]
+    StopMirroring,
+
+    #[serde(rename = "discovery")]
+    Discovery {},
+
+    #[serde(rename = "pointer_image")]
+    PointerImage { data: String },
+
+    #[serde(rename = "set_zoom_enabled")]
+    SetZoomEnabled { enabled: bool },
+
+    #[serde(rename = "mirror_response")]
+    MirrorResponse { device_id: String, accepted: bool },
+
+    #[serde(rename = "set_audio_sensitivity")]
+    SetAudioSensitivity { value: f32 },
+
+    #[serde(rename = "get_monitors")]
+    GetMonitors,
+
+    #[serde(rename = "set_auto_connect")]
+    SetAutoConnect { enabled: bool },
+    #[serde(rename = "set_device_auto_reconnect")]
+    SetDeviceAutoReconnect { id: String, enabled: bool },
+    #[serde(rename = "request_auto_reconnect")]
+    RequestAutoReconnect { id: String },
+    #[serde(rename = "auto_reconnect_response")]
+    AutoReconnectResponse { id: String, accepted: bool },
+    #[serde(rename = "pc_stop_mirroring")]
+    PCStopMirroring { id: String },
+
+    /// `token` is the content of `dashboard.token` in the server's config dir
+    #[serde(rename = "register_dashboard")]
+    RegisterDashboard { token: String },
+    /// Starts the `DashboardEvent` stream (registered dashboards only)
+    #[serde(rename = "subscribe_events")]
+    SubscribeEvents,
+
+    /// Asks for the QUIC media channel (needs `transport.quic`)
+    #[serde(rename = "open_media_channel")]
+    OpenMediaChannel,
+
+    // Out-of-band pairing
+    #[serde(rename = "start_pairing")]
+    StartPairing,
+    #[serde(rename = "cancel_pairing")]
+    CancelPairing,
+    #[serde(rename = "set_require_pairing_code")]
+    SetRequirePairingCode { enabled: bool },
+
+    // Per-device permission scopes
+    #[serde(rename = "get_device_permissions")]
+    GetDevicePermissions { id: String },
+    #[serde(rename = "set_device_permissions")]
+    SetDevicePermissions { id: String, permissions: BTreeSet<Permission> },
+
+    /// Security audit log, newest first. `before` is the `seq` to continue
+    /// below, from the previous page's `next_before`.
+    #[serde(rename = "get_audit_log")]
+    GetAuditLog {
+        #[serde(default)] before: Option<u64>,
+        #[serde(default = "default_audit_page")] limit: u32,
+    },
+
+    /// Replaces the server certificate. The current one stays in use for
+    /// `grace_secs` so paired devices can learn the new fingerprint.
+    #[serde(rename = "rotate_certificate")]
+    RotateCertificate { grace_secs: u64 },
+}
+
+impl InputEvent {
+    /// Scope a trusted device needs for this event. Management and pairing
+    /// events are not device actions and need none.
+    pub fn required_permission(&self) -> Option<Permission> {
+        match self.class() {
+            EventClass::Device(permission) => Some(permission),
+            EventClass::Housekeeping | EventClass::Management => None,
+        }
+    }
+
+    /// Events that manage the server rather than act as a device. Only
+    /// dashboards on the local control socket may send them.
+    pub fn is_management(&self) -> bool {
+        self.class() == EventClass::Management
+    }
+
+    /// Every variant is listed, so a new one has to be classed here before
+    /// it compiles and both gates above agree on it.
+    fn class(&self) -> EventClass {
+        match self {
+            InputEvent::Move { .. }
+            | InputEvent::MoveAbsolute { .. }
+            | InputEvent::Click { .. }
+            | InputEvent::MouseClick { .. }
+            | InputEvent::Scroll { .. }
+            | InputEvent::TouchFrame { .. }
+            | InputEvent::ScreenTouch { .. } => EventClass::Device(Permission::Input),
+            InputEvent::KeyPress { .. }
+            | InputEvent::KeyDown { .. }
+            | InputEvent::KeyUp { .. }
+            | InputEvent::KeyCombo { .. }
+            | InputEvent::TypeText { .. }
+            | InputEvent::PresentationControl { .. } => EventClass::Device(Permission::Keyboard),
+            InputEvent::MediaControl { .. }
+            | InputEvent::MediaGetStatus
+            | InputEvent::SetAudioSensitivity { .. } => EventClass::Device(Permission::Media),
+            InputEvent::PointerData { .. }
+            | InputEvent::TestOverlaySequence
+            | InputEvent::SetPointerMonitor { .. }
+            | InputEvent::PointerImage { .. } => EventClass::Device(Permission::Pointer),
+            InputEvent::LaunchApp { .. }
+            | InputEvent::GetApps => EventClass::Device(Permission::LaunchApp),
+            InputEvent::StartMirroring { .. }
+            | InputEvent::StopMirroring
+            | InputEvent::GetMonitors => EventClass::Device(Permission::Mirror),
+            InputEvent::PairRequest { .. }
+            | InputEvent::Discovery {}
+            | InputEvent::RegisterDashboard { .. }
+            | InputEvent::OpenMediaChannel
+            | InputEvent::RequestAutoReconnect { .. }
+            | InputEvent::SetDeviceAutoReconnect { .. } => EventClass::Housekeeping,
+            InputEvent::GetStatus
+            | InputEvent::ApproveDevice { .. }
+            | InputEvent::RejectDevice { .. }
+            | InputEvent::BlockDevice { .. }
+            | InputEvent::UnblockDevice { .. }
+            | InputEvent::SetZoomEnabled { .. }
+            | InputEvent::MirrorResponse { .. }
+            | InputEvent::SetAutoConnect { .. }
+            | InputEvent::AutoReconnectResponse { .. }
+            | InputEvent::PCStopMirroring { .. }
+            | InputEvent::SubscribeEvents
+            | InputEvent::StartPairing
+            | InputEvent::CancelPairing
+            | InputEvent::SetRequirePairingCode { .. }
+            | InputEvent::GetDevicePermissions { .. }
+            | InputEvent::SetDevicePermissions { .. }
+            | InputEvent::GetAuditLog { .. }
+            | InputEvent::RotateCertificate { .. } => EventClass::Management,
+        }
+    }
+}
+
+/// Who may send an event, see `InputEvent::class`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventClass {
+    /// A trusted device with this permission, or a dashboard
+    Device(Permission),
+    /// Pairing and connection housekeeping, allowed before trust
+    Housekeeping,
+    /// Dashboards only
+    Management,
+}
+
+/// Feature names exchanged in the pairing handshake. Names either side
+/// doesn't know are ignored, so new ones can be added without a version bump.
+pub mod capability {
+    pub const MIRRORING_JPEG: &str = "mirroring.jpeg";
+    pub const MIRRORING_H264: &str = "mirroring.h264";
+    pub const SPECTRUM_7BAND: &str = "spectrum.7band";
+    pub const POINTER_MULTI: &str = "pointer.multi";
+    pub const CLIPBOARD: &str = "clipboard";
+    pub const FILE_TRANSFER: &str = "file_transfer";
+    /// Spectrum and frames over QUIC datagrams instead of the TLS stream.
+    pub const TRANSPORT_QUIC: &str = "transport.quic";
+    /// Understands `ControlResponse::CertificateRotation`.
+    pub const CERT_ROTATION: &str = "security.cert_rotation";
+
+    /// Every name this protocol version defines.
+    pub const KNOWN: &[&str] = &[MIRRORING_JPEG, MIRRORING_H264, SPECTRUM_7BAND, POINTER_MULTI, CLIPBOARD, FILE_TRANSFER, TRANSPORT_QUIC, CERT_ROTATION];
+
+    /// What this server implements. Pointers from several devices are
+    /// always drawn independently, so there is nothing to negotiate for
+    /// `POINTER_MULTI` and it isn't offered.
+    pub const SERVER: &[&str] = &[MIRRORING_JPEG, SPECTRUM_7BAND, TRANSPORT_QUIC, CERT_ROTATION];
+
+    /// Assumed for clients that don't send a list: what they always received.
+    pub const LEGACY: &[&str] = &[MIRRORING_JPEG, SPECTRUM_7BAND];
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Input,
+    Keyboard,
+    Media,
+    Pointer,
+    LaunchApp,
+    Mirror,
+    /// No event needs it: the server has no clipboard sync. Kept so stored
+    /// permission sets that name it still load.
+    Clipboard,
+}
+
+impl Permission {
+    /// Every scope some event requires.
+    pub const ALL: [Permission; 6] = [
+        Permission::Input,
+        Permission::Keyboard,
+        Permission::Media,
+        Permission::Pointer,
+        Permission::LaunchApp,
+        Permission::Mirror,
+    ];
+}
+
+/// Devices paired before scopes existed keep the full set they always had.
+pub fn default_permissions() -> BTreeSet<Permission> {
+    Permission::ALL.into_iter().collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    PermissionDenied,
+    UnknownDevice,
+    /// The request needs a trusted device.
+    NotTrusted,
+    /// Writing to the virtual input device failed.
+    InputFailed,
+    /// No media player, or the player refused the command.
+    MediaFailed,
+    /// The command is not one of the installed applications.
+    UnverifiedApp,
+    LaunchFailed,
+    InvalidRequest,
+    /// Management commands need a dashboard on the local control socket.
+    NotDashboard,
+    /// A new server certificate could not be generated or stored.
+    RotationFailed,
+}
+
+fn default_size() -> f32 { 1.0 }
+fn default_color() -> String { "#ffffffff".to_string() }
+fn default_zoom() -> f32 { 1.0 }
+fn default_scale() -> f64 { 1.0 }
+fn default_particle() -> i32 { 0 }
+fn default_stretch() -> f32 { 1.0 }
+fn default_width() -> u32 { 854 }
+fn default_height() -> u32 { 480 }
+fn default_fps() -> u32 { 15 }
+fn default_pulse_speed() -> f32 { 1.0 }
+fn default_audit_page() -> u32 { 100 }
+
+/// One finger in a `TouchFrame`. `id` stays the same while the finger is
+/// down; `x` and `y` are 0.0-1.0 across the touch area.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TouchContact {
+    pub id: u32,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonitorInfo {
+    pub id: i32,
+    pub name: String,
+    /// Mode size in physical pixels
+    pub width: i32,
+    pub height: i32,
+    /// Position in the layout, which is in logical pixels
+    pub x: i32,
+    pub y: i32,
+    pub focused: bool,
+    /// Physical pixels per logical pixel
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// `wl_output` transform; odd values are rotated by 90° or 270°
+    #[serde(default)]
+    pub transform: i32,
+}
+
+impl MonitorInfo {
+    /// Width and height the monitor takes up in the layout.
+    pub fn logical_size(&self) -> (f64, f64) {
+        let scale = if self.scale > 0.0 { self.scale } else { 1.0 };
+        let (width, height) = (self.width as f64 / scale, self.height as f64 / scale);
+        if self.transform % 2 == 1 { (height, width) } else { (width, height) }
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppInfo {
+    pub name: String,
+    pub exec: String,
+    pub icon: String, // Can be name or path
+    pub icon_base64: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub status: String, // "Trusted", "Pending", "Connected"
+    pub ip: String,
+    #[serde(default)]
+    pub auto_reconnect: bool,
+    #[serde(default)]
+    pub is_mirroring: bool,
+    /// Fingerprint of the client certificate issued at pairing. Clearing it
+    /// revokes the certificate.
+    #[serde(default)]
+    pub cert_fingerprint: Option<String>,
+    #[serde(default = "default_permissions")]
+    pub permissions: BTreeSet<Permission>,
+}
+
+/// Security-relevant things the server did, as kept in its audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    PairRequest,
+    ApproveDevice,
+    RejectDevice,
+    BlockDevice,
+    LaunchApp,
+    StartMirroring,
+    MirrorResponse,
+    PointerImage,
+    RotateCertificate,
+}
+
+/// One line of the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// Increases by one per entry, across log rotations
+    pub seq: u64,
+    /// Unix time in milliseconds
+    pub time_ms: u64,
+    pub action: AuditAction,
+    pub device_id: String,
+    /// Where the device was connecting from, when known
+    #[serde(default)]
+    pub ip: Option<String>,
+    /// e.g. the pairing status sent back, "Launched", "Declined"
+    pub outcome: String,
+    /// Who decided, the command launched, ...
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MediaMetadata {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub art_url: String,
+    pub duration: i64, // Usecs
+    pub position: i64, // Usecs
+    pub status: String, // "Playing", "Paused", "Stopped"
+    pub player_name: String,
+    pub shuffle: bool,
+    pub repeat: String, // "None", "Track", "Playlist"
+    pub volume: f64,    // 0.0 to 1.0
+    pub track_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", content = "data")]
+pub enum ControlResponse {
+    #[serde(rename = "discovery_response")]
+    DiscoveryResponse { 
+        server_name: String, 
+        #[serde(default)]
+        fingerprint: Option<String> 
+    },
+    #[serde(rename = "media_status")]
+    MediaStatus { metadata: Option<MediaMetadata> },
+    #[serde(rename = "status_response")]
+    StatusResponse { 
+        devices: Vec<DeviceInfo>,
+        #[serde(default)]
+        zoom_enabled: bool,
+    },
+    #[serde(rename = "pair_response")]
+    PairResponse { 
+        status: String, 
+        server_version: String, 
+        server_name: String,
+        #[serde(default)]
+        fingerprint: Option<String>,
+        /// Everything the server implements; the session uses the intersection
+        #[serde(default)]
+        capabilities: Vec<String>,
+        #[serde(default)]
+        protocol_version: u32,
+    },
+    #[serde(rename = "apps_list")]
+    AppsList { apps: Vec<AppInfo> },
+    #[serde(rename = "mirror_request")]
+    MirrorRequest { device_id: String, device_name: String },
+    #[serde(rename = "mirror_status")]
+    MirrorStatus { allowed: bool, message: String },
+    #[serde(rename = "auto_reconnect_request")]
+    AutoReconnectRequest { device_id: String, device_name: String },
+    #[serde(rename = "stop_mirroring")]
+    StopMirroring,
+    #[serde(rename = "monitors_list")]
+    MonitorsList { monitors: Vec<MonitorInfo> },
+    #[serde(rename = "security_update")]
+    SecurityUpdate { status: String },
+    #[serde(rename = "register_response")]
+    RegisterResponse { success: bool },
+    /// Client identity issued on approval; the device presents it on every
+    /// later TLS handshake.
+    #[serde(rename = "client_certificate")]
+    ClientCertificate { cert_pem: String, key_pem: String, fingerprint: String },
+    #[serde(rename = "pairing_code")]
+    PairingCode { code: String, qr_payload: String, expires_in_secs: u64 },
+    /// Where to open the QUIC connection and the token that ties it to this session.
+    #[serde(rename = "media_channel")]
+    MediaChannel { port: u16, token: String },
+    #[serde(rename = "device_permissions")]
+    DevicePermissions { id: String, permissions: BTreeSet<Permission> },
+    #[serde(rename = "dashboard_event")]
+    DashboardEvent { event: DashboardEvent },
+    /// The server certificate is being replaced: from `switch_at_ms` (Unix
+    /// time) the server presents the one with `new_fingerprint`. `signature`
+    /// is hex, made with the key of `old_fingerprint` over
+    /// `rotation_statement(..)` using `scheme` (a TLS `SignatureScheme` name
+    /// such as "ECDSA_NISTP256_SHA256"), so a device that pinned the old
+    /// certificate can re-pin without pairing again.
+    #[serde(rename = "certificate_rotation")]
+    CertificateRotation {
+        old_fingerprint: String,
+        new_fingerprint: String,
+        switch_at_ms: u64,
+        scheme: String,
+        signature: String,
+    },
+    /// A page of the audit log; `next_before` is `None` on the last page.
+    #[serde(rename = "audit_log")]
+    AuditLog { entries: Vec<AuditEntry>, next_before: Option<u64> },
+    #[serde(rename = "error")]
+    Error { code: ErrorCode, message: String },
+    #[serde(rename = "ack")]
+    Ack {
+        request_id: u64,
+        ok: bool,
+        error_code: Option<ErrorCode>,
+        message: Option<String>,
+    },
+}
+
+impl ControlResponse {
+    /// Capability the peer must have negotiated to receive this response.
+    pub fn required_capability(&self) -> Option<&'static str> {
+        match self {
+            ControlResponse::MirrorStatus { .. }
+            | ControlResponse::StopMirroring => Some(capability::MIRRORING_JPEG),
+            ControlResponse::CertificateRotation { .. } => Some(capability::CERT_ROTATION),
+            _ => None,
+        }
+    }
+}
+
+/// The bytes signed in `ControlResponse::CertificateRotation`.
+pub fn rotation_statement(old_fingerprint: &str, new_fingerprint: &str, switch_at_ms: u64) -> Vec<u8> {
+    format!("wayland-connect certificate rotation\n{}\n{}\n{}", old_fingerprint, new_fingerprint, switch_at_ms).into_bytes()
+}
+
+/// Pushed to dashboards that sent `SubscribeEvents`, as things happen.
+/// A `StatusResponse` is sent on subscribe as the starting point.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", content = "data")]
+pub enum DashboardEvent {
+    /// A connection paired as `id`; the device may still be pending.
+    #[serde(rename = "device_connected")]
+    DeviceConnected { id: String, name: String, ip: String },
+    #[serde(rename = "device_disconnected")]
+    DeviceDisconnected { id: String },
+    /// Anything stored about the device changed (status, permissions, ...).
+    #[serde(rename = "device_status_changed")]
+    DeviceStatusChanged { device: DeviceInfo },
+    #[serde(rename = "device_removed")]
+    DeviceRemoved { id: String },
+    #[serde(rename = "mirroring_started")]
+    MirroringStarted { device_id: String },
+    #[serde(rename = "mirroring_stopped")]
+    MirroringStopped { device_id: String },
+    #[serde(rename = "pointer_active")]
+    PointerActive { device_id: String, active: bool },
+    /// The player, track or playback status changed.
+    #[serde(rename = "media_changed")]
+    MediaChanged { metadata: Option<MediaMetadata> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "t", content = "d")]
+pub enum BinaryPacket {
+    #[serde(rename = "s")]
+    Spectrum { bands: Vec<f32> },
+    #[serde(rename = "f")]
+    Frame { b: Vec<u8> },
+}
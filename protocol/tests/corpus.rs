@@ -0,0 +1,42 @@
+//! Replays the checked-in fuzz corpora (`fuzz/corpus`) on stable, so inputs
+//! that once broke the framer or decoder keep getting exercised by
+//! `cargo test`. New crashes found with `cargo fuzz` belong in there too.
+
+use std::path::PathBuf;
+use wc_protocol::{framing, ClientMessage};
+
+fn corpus(target: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../fuzz/corpus").join(target);
+    let mut inputs: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("missing corpus {:?}: {}", dir, e))
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            (path.file_name().unwrap().to_string_lossy().into_owned(), std::fs::read(&path).unwrap())
+        })
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "corpus {:?} is empty", dir);
+    inputs
+}
+
+#[test]
+fn client_message_corpus() {
+    for (name, data) in corpus("client_message") {
+        let Ok(message) = framing::decode::<ClientMessage>(&data) else { continue };
+        let encoded = framing::encode_body(&message).unwrap_or_else(|e| panic!("'{}' does not re-encode: {}", name, e));
+        if let Err(e) = framing::decode::<ClientMessage>(&encoded) {
+            panic!("'{}' does not decode after re-encoding: {}", name, e);
+        }
+    }
+}
+
+#[tokio::test]
+async fn read_frame_corpus() {
+    for (_, data) in corpus("read_frame") {
+        let mut input = data.as_slice();
+        while let Ok(body) = framing::read_frame_limited(&mut input, framing::MAX_PREAUTH_FRAME_LEN).await {
+            assert!(body.len() <= framing::MAX_PREAUTH_FRAME_LEN);
+            let _ = framing::decode::<ClientMessage>(&body);
+        }
+    }
+}
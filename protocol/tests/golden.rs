@@ -0,0 +1,239 @@
+//! Pins the byte encoding of representative messages. The Android and
+//! desktop clients decode these exact bytes, so a diff here is a wire break.
+//!
+//! Regenerate after an intentional change with `UPDATE_GOLDEN=1 cargo test -p wc_protocol`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use wc_protocol::{framing, AuditAction, AuditEntry, BinaryPacket, ClientMessage, ControlResponse, DashboardEvent, ErrorCode, InputEvent, Permission, TouchContact};
+
+fn check<T: Serialize + DeserializeOwned>(name: &str, message: &T) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.bin", name));
+    let encoded = framing::encode(message).unwrap();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &encoded).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read(&path).unwrap_or_else(|e| panic!("missing golden file {:?}: {}", path, e));
+    assert_eq!(encoded, golden, "encoding of '{}' changed", name);
+
+    // The golden bytes must also decode and re-encode to themselves
+    let decoded: T = framing::decode(&golden[4..]).unwrap();
+    assert_eq!(framing::encode(&decoded).unwrap(), golden, "'{}' does not round-trip", name);
+}
+
+#[test]
+fn client_move() {
+    check("client_move", &ClientMessage {
+        event: InputEvent::Move { dx: 1.5, dy: -2.0 },
+        request_id: None,
+    });
+}
+
+#[test]
+fn client_get_status_with_request_id() {
+    check("client_get_status", &ClientMessage {
+        event: InputEvent::GetStatus,
+        request_id: Some(7),
+    });
+}
+
+#[test]
+fn client_key_combo() {
+    check("client_key_combo", &ClientMessage {
+        event: InputEvent::KeyCombo { modifiers: vec!["Ctrl".to_string(), "Shift".to_string()], key: "t".to_string() },
+        request_id: Some(3),
+    });
+}
+
+#[test]
+fn client_type_text() {
+    check("client_type_text", &ClientMessage {
+        event: InputEvent::TypeText { text: "Grüße, 世界 🙂\n".to_string() },
+        request_id: None,
+    });
+}
+
+#[test]
+fn client_scroll() {
+    check("client_scroll", &ClientMessage {
+        event: InputEvent::Scroll { dx: -0.25, dy: 1.5, precise: true },
+        request_id: None,
+    });
+}
+
+#[test]
+fn client_touch_frame() {
+    check("client_touch_frame", &ClientMessage {
+        event: InputEvent::TouchFrame {
+            contacts: vec![TouchContact { id: 0, x: 0.25, y: 0.5 }, TouchContact { id: 3, x: 0.75, y: 0.125 }],
+        },
+        request_id: Some(9),
+    });
+}
+
+#[test]
+fn client_screen_touch() {
+    check("client_screen_touch", &ClientMessage {
+        event: InputEvent::ScreenTouch { x: 0.5, y: 0.25, state: "down".to_string() },
+        request_id: Some(10),
+    });
+}
+
+#[test]
+fn client_pair_request() {
+    check("client_pair_request", &ClientMessage {
+        event: InputEvent::PairRequest {
+            device_name: "Pixel 8".to_string(),
+            id: "device-1".to_string(),
+            version: "1.0.1".to_string(),
+            auto_reconnect: Some(true),
+            pairing_proof: None,
+            capabilities: Some(vec!["mirroring.jpeg".to_string(), "spectrum.7band".to_string()]),
+        },
+        request_id: None,
+    });
+}
+
+#[test]
+fn client_set_device_permissions() {
+    check("client_set_device_permissions", &ClientMessage {
+        event: InputEvent::SetDevicePermissions {
+            id: "device-1".to_string(),
+            permissions: BTreeSet::from([Permission::Input, Permission::Media]),
+        },
+        request_id: Some(1),
+    });
+}
+
+#[test]
+fn control_pair_response() {
+    check("control_pair_response", &ControlResponse::PairResponse {
+        status: "Trusted".to_string(),
+        server_version: "1.0.1".to_string(),
+        server_name: "desktop".to_string(),
+        fingerprint: Some("AB:CD".to_string()),
+        capabilities: vec!["mirroring.jpeg".to_string()],
+        protocol_version: 1,
+    });
+}
+
+#[test]
+fn control_ack() {
+    check("control_ack_ok", &ControlResponse::Ack {
+        request_id: 7,
+        ok: true,
+        error_code: None,
+        message: None,
+    });
+    check("control_ack_error", &ControlResponse::Ack {
+        request_id: 8,
+        ok: false,
+        error_code: Some(ErrorCode::UnverifiedApp),
+        message: Some("'rm' is not an installed application".to_string()),
+    });
+}
+
+#[test]
+fn control_error() {
+    check("control_error", &ControlResponse::Error {
+        code: ErrorCode::PermissionDenied,
+        message: "Device is not allowed to use Media".to_string(),
+    });
+}
+
+#[test]
+fn control_stop_mirroring() {
+    check("control_stop_mirroring", &ControlResponse::StopMirroring);
+}
+
+#[test]
+fn control_dashboard_event() {
+    check("control_dashboard_event", &ControlResponse::DashboardEvent {
+        event: DashboardEvent::PointerActive { device_id: "device-1".to_string(), active: true },
+    });
+}
+
+#[test]
+fn control_audit_log() {
+    check("control_audit_log", &ControlResponse::AuditLog {
+        entries: vec![AuditEntry {
+            seq: 41,
+            time_ms: 1_700_000_000_000,
+            action: AuditAction::LaunchApp,
+            device_id: "device-1".to_string(),
+            ip: Some("192.168.1.20".to_string()),
+            outcome: "Launched".to_string(),
+            detail: Some("firefox".to_string()),
+        }],
+        next_before: Some(41),
+    });
+}
+
+#[test]
+fn binary_spectrum() {
+    check("binary_spectrum", &BinaryPacket::Spectrum { bands: vec![0.0, 0.25, 0.5, 0.75, 1.0, 0.5, 0.125] });
+}
+
+#[test]
+fn binary_frame() {
+    check("binary_frame", &BinaryPacket::Frame { b: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10] });
+}
+
+#[tokio::test]
+async fn read_frame_rejects_oversized_frames() {
+    let mut input: &[u8] = &((framing::MAX_FRAME_LEN as u32) + 1).to_be_bytes();
+    let err = framing::read_frame(&mut input).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn read_frame_limited_enforces_the_given_limit() {
+    let body = vec![0xC0u8; framing::MAX_PREAUTH_FRAME_LEN];
+    let frame = framing::frame(&body);
+    let mut input: &[u8] = &frame;
+    assert_eq!(framing::read_frame_limited(&mut input, framing::MAX_PREAUTH_FRAME_LEN).await.unwrap(), body);
+
+    let frame = framing::frame(&[body.as_slice(), &[0xC0]].concat());
+    let mut input: &[u8] = &frame;
+    let err = framing::read_frame_limited(&mut input, framing::MAX_PREAUTH_FRAME_LEN).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn read_frame_reports_truncated_frames() {
+    let mut frame = (framing::MAX_FRAME_LEN as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(b"short");
+    let mut input: &[u8] = &frame;
+    let err = framing::read_frame(&mut input).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn control_certificate_rotation() {
+    check("control_certificate_rotation", &ControlResponse::CertificateRotation {
+        old_fingerprint: "AA:BB:CC".to_string(),
+        new_fingerprint: "DD:EE:FF".to_string(),
+        switch_at_ms: 1_700_086_400_000,
+        scheme: "ECDSA_NISTP256_SHA256".to_string(),
+        signature: "3045022100ab".to_string(),
+    });
+}
+
+#[test]
+fn legacy_scroll_is_vertical_and_coarse() {
+    // What clients sent before `dx` and `precise` existed
+    #[derive(Serialize)]
+    #[serde(tag = "type", content = "data")]
+    enum Legacy {
+        #[serde(rename = "scroll")]
+        Scroll { dy: f64 },
+    }
+    let encoded = framing::encode(&Legacy::Scroll { dy: 3.0 }).unwrap();
+    let message: ClientMessage = framing::decode(&encoded[4..]).unwrap();
+    assert!(matches!(message.event, InputEvent::Scroll { dx, dy, precise: false } if dx == 0.0 && dy == 3.0));
+}
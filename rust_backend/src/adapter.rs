@@ -1,16 +1,36 @@
-use crate::protocol::InputEvent as ProtocolEvent;
+use wc_protocol::InputEvent as ProtocolEvent;
 use async_trait::async_trait;
-use input_linux::{UInputHandle, EventKind, RelativeAxis, AbsoluteAxis, AbsoluteInfo, AbsoluteInfoSetup, Key, InputId};
+use input_linux::{UInputHandle, EventKind, RelativeAxis, AbsoluteAxis, AbsoluteInfo, AbsoluteInfoSetup, Key, InputId, InputProperty};
+use log::warn;
+use std::collections::HashMap;
 use std::fs::File;
+use std::sync::Mutex;
+use crate::text_input::TextTyper;
+use crate::touch::{MultitouchSlots, MAX_CONTACTS, MAX_TRACKING_ID};
+use crate::xkb::Stroke;
+use wc_core::scroll::ScrollAccumulator;
 
 // Trait to decouple the application from the specific desktop environment mechanism
 #[async_trait]
 pub trait InputAdapter {
-    async fn send_event(&self, event: ProtocolEvent) -> anyhow::Result<()>;
+    /// `source` tells concurrent senders (sessions) apart, so one phone's
+    /// fingers and scroll remainders don't mix with another's.
+    async fn send_event(&self, source: &str, event: ProtocolEvent) -> anyhow::Result<()>;
+
+    /// Drops what is kept for `source` once it is gone.
+    fn forget(&self, _source: &str) {}
 }
 
 pub struct UInputAdapter {
     handle: UInputHandle<File>,
+    /// Layout and compositor connection for `TypeText`, set up on first use
+    text_input: TextTyper,
+    /// Sub-step scroll remainders per source, horizontal then vertical
+    scroll: Mutex<HashMap<String, [ScrollAccumulator; 2]>>,
+    /// For `TouchFrame`; `None` if the device couldn't be created
+    touchpad: Option<VirtualTouchDevice>,
+    /// For `ScreenTouch`; `None` if the device couldn't be created
+    touchscreen: Option<VirtualTouchDevice>,
 }
 
 impl UInputAdapter {
@@ -23,6 +43,10 @@ impl UInputAdapter {
         handle.set_relbit(RelativeAxis::X)?;
         handle.set_relbit(RelativeAxis::Y)?;
         handle.set_relbit(RelativeAxis::Wheel)?; // Enable scroll wheel
+        handle.set_relbit(RelativeAxis::HorizontalWheel)?;
+        // 120ths of a notch, for smooth scrolling
+        handle.set_relbit(RelativeAxis::WheelHiRes)?;
+        handle.set_relbit(RelativeAxis::HorizontalWheelHiRes)?;
 
         // Setup Absolute Axis (for mirroring/touchscreen)
         handle.set_evbit(EventKind::Absolute)?;
@@ -50,13 +74,11 @@ impl UInputAdapter {
 
         // Enable Mouse Buttons (Key Events)
         handle.set_evbit(EventKind::Key)?;
-        // Enable Keyboard bits for a-z, 0-9, and common keys
-        for key_code in 1..=120 {
-            if let Ok(k) = Key::from_code(key_code) {
-                handle.set_keybit(k).ok(); 
-            }
+        // Every keyboard key, so modifiers, F-keys and media keys work too
+        for key in crate::keymap::keyboard_keys() {
+            handle.set_keybit(key)?;
         }
-        // Specifically ensure Mouse buttons are enabled (they are > 120 usually)
+        // Mouse buttons live outside the keyboard range
         handle.set_keybit(Key::ButtonLeft)?;
         handle.set_keybit(Key::ButtonRight)?;
         handle.set_keybit(Key::ButtonMiddle)?;
@@ -70,7 +92,14 @@ impl UInputAdapter {
         
         handle.create(&id, b"WaylandConnect Virtual Mouse", 0, &abs_setup)?;
         
-        Ok(Self { handle })
+        let touchpad = VirtualTouchDevice::touchpad()
+            .map_err(|e| warn!("⚠️ No virtual touchpad, touch gestures are disabled: {}", e))
+            .ok();
+        let touchscreen = VirtualTouchDevice::touchscreen()
+            .map_err(|e| warn!("⚠️ No virtual touchscreen, taps on the mirrored screen are disabled: {}", e))
+            .ok();
+
+        Ok(Self { handle, text_input: TextTyper::spawn()?, scroll: Mutex::new(Default::default()), touchpad, touchscreen })
     }
     
     // Helper to send raw event safely
@@ -94,11 +123,33 @@ impl UInputAdapter {
         self.handle.write(&events).map_err(|e| anyhow::anyhow!("Failed to write to uinput: {}", e))?;
         Ok(())
     }
+
+    /// EV_KEY press (1) or release (0)
+    fn write_key(&self, key: Key, value: i32) -> anyhow::Result<()> {
+        self.write_raw(1, key as u16, value)
+    }
+
+    /// Taps `stroke.key` with Shift and AltGr held as needed.
+    fn write_stroke(&self, stroke: Stroke) -> anyhow::Result<()> {
+        let modifiers: Vec<Key> = [(stroke.shift, Key::LeftShift), (stroke.level3, Key::RightAlt)]
+            .into_iter()
+            .filter_map(|(held, key)| held.then_some(key))
+            .collect();
+        for modifier in &modifiers {
+            self.write_key(*modifier, 1)?;
+        }
+        self.write_raw(1, stroke.key, 1)?;
+        self.write_raw(1, stroke.key, 0)?;
+        for modifier in modifiers.iter().rev() {
+            self.write_key(*modifier, 0)?;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl InputAdapter for UInputAdapter {
-    async fn send_event(&self, event: ProtocolEvent) -> anyhow::Result<()> {
+    async fn send_event(&self, source: &str, event: ProtocolEvent) -> anyhow::Result<()> {
         match event {
             ProtocolEvent::Move { dx, dy } => {
                 let multi = wc_core::constants::MOUSE_SENSITIVITY;
@@ -155,72 +206,179 @@ impl InputAdapter for UInputAdapter {
                 };
                 self.write_raw(1, code, value)?;
             }
-            ProtocolEvent::Scroll { dy } => {
-                // EV_REL (2), REL_WHEEL (8)
-                // Remove inversion and hard multiplier to let frontend decide speed/direction
-                let amount = dy as i32;
-                self.write_raw(2, 8, amount)?;
+            ProtocolEvent::Scroll { dx, dy, precise } => {
+                let [horizontal, vertical] = {
+                    let mut scroll = self.scroll.lock().unwrap();
+                    let scroll = scroll.entry(source.to_string()).or_default();
+                    [scroll[0].feed(dx, precise), scroll[1].feed(dy, precise)]
+                };
+                let axes = [
+                    (RelativeAxis::HorizontalWheelHiRes, horizontal.hi_res),
+                    (RelativeAxis::HorizontalWheel, horizontal.notches),
+                    (RelativeAxis::WheelHiRes, vertical.hi_res),
+                    (RelativeAxis::Wheel, vertical.notches),
+                ];
+                let mut events: Vec<_> = axes.iter()
+                    .filter(|(_, value)| *value != 0)
+                    .map(|(axis, value)| input_linux::sys::input_event {
+                        time: input_linux::sys::timeval { tv_sec: 0, tv_usec: 0 },
+                        type_: 2, code: *axis as u16, value: *value
+                    })
+                    .collect();
+                if events.is_empty() { return Ok(()); }
+                events.push(input_linux::sys::input_event {
+                    time: input_linux::sys::timeval { tv_sec: 0, tv_usec: 0 },
+                    type_: 0, code: 0, value: 0
+                });
+                self.handle.write(&events).map_err(|e| anyhow::anyhow!("Failed to write to uinput: {}", e))?;
             }
             ProtocolEvent::KeyPress { key } => {
-                let code = match key.as_str() {
-                    "Enter" => Some(28),
-                    "Escape" => Some(1),
-                    "Backspace" => Some(14),
-                    "Tab" => Some(15),
-                    " " => Some(57), // Space
-                    s if s.len() == 1 => {
-                        if let Some(c) = s.chars().next() {
-                            let lower = c.to_ascii_lowercase();
-                            match lower {
-                                'a'..='z' => {
-                                    match lower {
-                                        'a' => Some(30), 'b' => Some(48), 'c' => Some(46), 'd' => Some(32),
-                                    'e' => Some(18), 'f' => Some(33), 'g' => Some(34), 'h' => Some(35),
-                                    'i' => Some(23), 'j' => Some(36), 'k' => Some(37), 'l' => Some(38),
-                                    'm' => Some(50), 'n' => Some(49), 'o' => Some(24), 'p' => Some(25),
-                                    'q' => Some(16), 'r' => Some(19), 's' => Some(31), 't' => Some(20),
-                                    'u' => Some(22), 'v' => Some(47), 'w' => Some(17), 'x' => Some(45),
-                                    'y' => Some(21), 'z' => Some(44),
-                                    _ => None,
-                                }
-                            }
-                            '1'..='9' | '0' => {
-                                match lower {
-                                    '1' => Some(2), '2' => Some(3), '3' => Some(4), '4' => Some(5),
-                                    '5' => Some(6), '6' => Some(7), '7' => Some(8), '8' => Some(9),
-                                    '9' => Some(10), '0' => Some(11),
-                                    _ => None,
-                                }
-                            }
-                            ',' => Some(51), '.' => Some(52), '/' => Some(53), ';' => Some(39),
-                            '\'' => Some(40), '[' => Some(26), ']' => Some(27), '-' => Some(12),
-                            '=' => Some(13), '\\' => Some(43), '`' => Some(41),
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    }
+                if let Some(key) = crate::keymap::lookup(&key) {
+                    self.write_key(key, 1)?;
+                    self.write_key(key, 0)?;
+                }
+            }
+            ProtocolEvent::KeyDown { key } => {
+                if let Some(key) = crate::keymap::lookup(&key) {
+                    self.write_key(key, 1)?;
                 }
-                    "Left" => Some(105),
-                    "Right" => Some(106),
-                    "Up" => Some(103),
-                    "Down" => Some(108),
-                    "Prior" | "PageUp" => Some(104),
-                    "Next" | "PageDown" => Some(109),
-                    "Home" => Some(102),
-                    "End" => Some(107),
-                    "Insert" => Some(110),
-                    "Delete" => Some(111),
-                    _ => None,
+            }
+            ProtocolEvent::KeyUp { key } => {
+                if let Some(key) = crate::keymap::lookup(&key) {
+                    self.write_key(key, 0)?;
+                }
+            }
+            ProtocolEvent::KeyCombo { modifiers, key } => {
+                let modifiers: Vec<Key> = modifiers.iter().filter_map(|m| crate::keymap::lookup(m)).collect();
+                let Some(key) = crate::keymap::lookup(&key) else { return Ok(()) };
+                for modifier in &modifiers {
+                    self.write_key(*modifier, 1)?;
+                }
+                self.write_key(key, 1)?;
+                self.write_key(key, 0)?;
+                for modifier in modifiers.iter().rev() {
+                    self.write_key(*modifier, 0)?;
+                }
+            }
+            ProtocolEvent::TypeText { text } => {
+                for stroke in self.text_input.prepare(text).await? {
+                    self.write_stroke(stroke)?;
+                }
+            }
+            ProtocolEvent::TouchFrame { contacts } => {
+                let Some(touchpad) = &self.touchpad else {
+                    anyhow::bail!("No virtual touchpad");
                 };
-
-                if let Some(c) = code {
-                    self.write_raw(1, c as u16, 1)?; // Press
-                    self.write_raw(1, c as u16, 0)?; // Release
+                touchpad.frame(source, &contacts)?;
+            }
+            ProtocolEvent::ScreenTouch { x, y, state } => {
+                let Some(touchscreen) = &self.touchscreen else {
+                    anyhow::bail!("No virtual touchscreen");
+                };
+                match state.as_str() {
+                    "down" | "move" => touchscreen.frame(source, &[wc_protocol::TouchContact { id: 0, x, y }])?,
+                    "up" => touchscreen.frame(source, &[])?,
+                    _ => {}
                 }
             }
             _ => {}
         }
         Ok(())
     }
+
+    fn forget(&self, source: &str) {
+        self.scroll.lock().unwrap().remove(source);
+    }
+}
+
+/// Touchpad size in device units; with `TOUCHPAD_RESOLUTION` units per mm
+/// that is a 100 x 65 mm pad, about what libinput expects of a laptop.
+const TOUCHPAD_MAX_X: i32 = 4000;
+const TOUCHPAD_MAX_Y: i32 = 2600;
+const TOUCHPAD_RESOLUTION: i32 = 40;
+/// Touchscreen range on both axes, spread over the whole desktop layout.
+const TOUCHSCREEN_MAX: i32 = 32767;
+
+/// A uinput multitouch device besides the mouse: a laptop-style touchpad,
+/// so phone touches reach the compositor as real multi-finger gestures
+/// (workspace swipes, pinch, natural scrolling) rather than mouse motion,
+/// or a touchscreen for tapping directly on the mirrored screen.
+pub struct VirtualTouchDevice {
+    handle: UInputHandle<File>,
+    slots: Mutex<MultitouchSlots>,
+    max_x: i32,
+    max_y: i32,
+}
+
+impl VirtualTouchDevice {
+    pub fn touchpad() -> anyhow::Result<Self> {
+        let keys = [
+            Key::ButtonLeft,
+            Key::ButtonTouch,
+            Key::ButtonToolFinger,
+            Key::ButtonToolDoubleTap,
+            Key::ButtonToolTripleTap,
+            Key::ButtonToolQuadtap,
+            Key::ButtonToolQuintTap,
+        ];
+        Self::create(b"WaylandConnect Virtual Touchpad", 0x5679, InputProperty::Pointer, &keys, MAX_CONTACTS,
+            TOUCHPAD_MAX_X, TOUCHPAD_MAX_Y, TOUCHPAD_RESOLUTION)
+    }
+
+    /// A single-finger touchscreen. Compositors that don't tie it to an
+    /// output map its range over the bounding box of all monitors, so
+    /// positions are given in that space (see `crate::touch::ScreenArea`).
+    /// Long-presses and drags are plain touch sequences to applications.
+    pub fn touchscreen() -> anyhow::Result<Self> {
+        Self::create(b"WaylandConnect Virtual Touchscreen", 0x567a, InputProperty::Direct, &[Key::ButtonTouch], 1,
+            TOUCHSCREEN_MAX, TOUCHSCREEN_MAX, 0)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create(name: &[u8], product: u16, property: InputProperty, keys: &[Key], slots: usize, max_x: i32, max_y: i32, resolution: i32) -> anyhow::Result<Self> {
+        let file = File::options().write(true).open(wc_core::constants::UINPUT_PATH)?;
+        let handle = UInputHandle::new(file);
+
+        handle.set_propbit(property)?;
+        handle.set_evbit(EventKind::Key)?;
+        for key in keys {
+            handle.set_keybit(*key)?;
+        }
+
+        handle.set_evbit(EventKind::Absolute)?;
+        let range = |maximum, resolution| AbsoluteInfo { value: 0, minimum: 0, maximum, fuzz: 0, flat: 0, resolution };
+        let abs_setup = [
+            (AbsoluteAxis::X, range(max_x, resolution)),
+            (AbsoluteAxis::Y, range(max_y, resolution)),
+            (AbsoluteAxis::MultitouchSlot, range(slots as i32 - 1, 0)),
+            (AbsoluteAxis::MultitouchTrackingId, range(MAX_TRACKING_ID, 0)),
+            (AbsoluteAxis::MultitouchPositionX, range(max_x, resolution)),
+            (AbsoluteAxis::MultitouchPositionY, range(max_y, resolution)),
+        ].map(|(axis, info)| AbsoluteInfoSetup { axis, info });
+        for setup in &abs_setup {
+            handle.set_absbit(setup.axis)?;
+        }
+
+        let id = InputId {
+            bustype: 0x03, // BUS_USB
+            vendor: 0x1234,
+            product,
+            version: 0,
+        };
+        handle.create(&id, name, 0, &abs_setup)?;
+        Ok(Self { handle, slots: Mutex::new(MultitouchSlots::default()), max_x, max_y })
+    }
+
+    pub fn frame(&self, source: &str, contacts: &[wc_protocol::TouchContact]) -> anyhow::Result<()> {
+        let events = self.slots.lock().unwrap().frame(source, contacts, self.max_x, self.max_y);
+        let events: Vec<_> = events.into_iter()
+            .chain([(0, 0, 0)]) // SYN_REPORT
+            .map(|(type_, code, value)| input_linux::sys::input_event {
+                time: input_linux::sys::timeval { tv_sec: 0, tv_usec: 0 },
+                type_, code, value,
+            })
+            .collect();
+        self.handle.write(&events).map_err(|e| anyhow::anyhow!("Failed to write to uinput: {}", e))?;
+        Ok(())
+    }
 }
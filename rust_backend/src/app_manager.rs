@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::protocol::AppInfo;
+use wc_protocol::AppInfo;
 
 pub struct AppManager {}
 
@@ -138,3 +138,9 @@ impl AppManager {
         }
     }
 }
+
+impl crate::services::AppCatalog for AppManager {
+    fn installed_apps(&self) -> Vec<AppInfo> {
+        Self::get_installed_apps()
+    }
+}
@@ -11,6 +11,12 @@ pub struct AudioAnalyzer {
     sensitivity: Arc<Mutex<f32>>,
 }
 
+impl Default for AudioAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AudioAnalyzer {
     pub fn new() -> Self {
         Self {
@@ -159,6 +165,20 @@ impl AudioAnalyzer {
     }
 }
 
+impl crate::services::SpectrumSource for AudioAnalyzer {
+    fn levels(&self) -> Vec<f32> {
+        self.get_levels()
+    }
+
+    fn set_sensitivity(&self, value: f32) {
+        AudioAnalyzer::set_sensitivity(self, value);
+    }
+
+    fn set_target_app(&self, app: Option<String>) {
+        AudioAnalyzer::set_target_app(self, app);
+    }
+}
+
 fn find_our_node() -> Option<String> {
     if let Ok(out) = Command::new("wpctl").arg("status").output() {
         let stdout = String::from_utf8_lossy(&out.stdout);
@@ -0,0 +1,177 @@
+use log::{error, info};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wc_protocol::{AuditAction, AuditEntry};
+
+const AUDIT_FILE: &str = "audit.log";
+/// Size at which the log is rotated to `audit.log.1`.
+const MAX_FILE_LEN: u64 = 1024 * 1024;
+/// Rotated files kept besides the current one.
+const KEEP_ROTATED: usize = 5;
+/// Largest page a dashboard can ask for.
+pub const MAX_PAGE: usize = 500;
+
+struct Writer {
+    path: PathBuf,
+    rotation: Arc<RwLock<()>>,
+    file: Option<File>,
+    len: u64,
+    next_seq: u64,
+}
+
+enum Job {
+    Append(AuditEntry),
+    /// Answered once every entry queued before it is on disk
+    Flush(mpsc::Sender<()>),
+}
+
+/// Append-only record of pairing decisions and privileged actions, one JSON
+/// `AuditEntry` per line in `audit.log` under the config dir.
+///
+/// Entries are only ever appended; when the file passes `MAX_FILE_LEN` it
+/// becomes `audit.log.1` (and so on, up to `KEEP_ROTATED`) and a new one is
+/// started. Writing happens on its own thread so recording never blocks a
+/// handler, and failing to write is logged but never stops the action itself.
+pub struct AuditLog {
+    path: PathBuf,
+    /// Held for writing only while rotation renames files, so readers never
+    /// see them move
+    rotation: Arc<RwLock<()>>,
+    jobs: mpsc::Sender<Job>,
+}
+
+impl AuditLog {
+    pub fn open(config_dir: &Path) -> Self {
+        let path = config_dir.join(AUDIT_FILE);
+        let last_seq = (0..=KEEP_ROTATED)
+            .find_map(|n| read_entries(&rotated_path(&path, n)).last().map(|e| e.seq));
+        let len = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let rotation = Arc::new(RwLock::new(()));
+        let mut writer = Writer {
+            path: path.clone(),
+            rotation: rotation.clone(),
+            file: None,
+            len,
+            next_seq: last_seq.map_or(0, |seq| seq + 1),
+        };
+        let (jobs, rx) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || writer.run(rx));
+        if let Err(e) = spawned {
+            error!("❌ Failed to start audit log writer: {}", e);
+        }
+        Self { path, rotation, jobs }
+    }
+
+    /// Queues an entry; its `seq` is assigned when it is written.
+    pub fn record(&self, action: AuditAction, device_id: &str, ip: Option<&str>, outcome: &str, detail: Option<String>) {
+        let entry = AuditEntry {
+            seq: 0,
+            time_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64),
+            action,
+            device_id: device_id.to_string(),
+            ip: ip.map(str::to_string),
+            outcome: outcome.to_string(),
+            detail,
+        };
+        info!("📜 Audit: {:?} {} from {:?}: {}", entry.action, entry.device_id, entry.ip, entry.outcome);
+        let _ = self.jobs.send(Job::Append(entry));
+    }
+
+    /// Blocks until every entry recorded so far is written.
+    pub fn flush(&self) {
+        let (done, flushed) = mpsc::channel();
+        if self.jobs.send(Job::Flush(done)).is_ok() {
+            let _ = flushed.recv();
+        }
+    }
+
+    /// Up to `limit` entries with `seq` below `before`, newest first, and the
+    /// `before` for the next page if there may be one. Reads files, so call
+    /// it from a blocking context.
+    pub fn page(&self, before: Option<u64>, limit: usize) -> (Vec<AuditEntry>, Option<u64>) {
+        let limit = limit.clamp(1, MAX_PAGE);
+        self.flush();
+        let _rotation = self.rotation.read().unwrap();
+        let mut entries = Vec::new();
+        for n in 0..=KEEP_ROTATED {
+            let older = read_entries(&rotated_path(&self.path, n)).into_iter().rev()
+                .filter(|e| before.is_none_or(|before| e.seq < before));
+            entries.extend(older.take(limit - entries.len()));
+            if entries.len() == limit {
+                break;
+            }
+        }
+        let next_before = entries.last().filter(|e| entries.len() == limit && e.seq > 0).map(|e| e.seq);
+        (entries, next_before)
+    }
+}
+
+impl Writer {
+    fn run(&mut self, jobs: mpsc::Receiver<Job>) {
+        for job in jobs {
+            match job {
+                Job::Append(mut entry) => {
+                    entry.seq = self.next_seq;
+                    let Ok(mut line) = serde_json::to_string(&entry) else { continue };
+                    line.push('\n');
+                    if let Err(e) = self.append(line.as_bytes()) {
+                        error!("❌ Failed to write audit log {:?}: {}", self.path, e);
+                        self.file = None;
+                        continue;
+                    }
+                    self.next_seq += 1;
+                },
+                Job::Flush(done) => {
+                    let _ = done.send(());
+                },
+            }
+        }
+    }
+
+    fn append(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.len > 0 && self.len + line.len() as u64 > MAX_FILE_LEN {
+            self.file = None;
+            let _rotation = self.rotation.write().unwrap();
+            for n in (1..=KEEP_ROTATED).rev() {
+                let from = rotated_path(&self.path, n - 1);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n))?;
+                }
+            }
+            self.len = 0;
+        }
+        if self.file.is_none() {
+            self.file = Some(OpenOptions::new().create(true).append(true).mode(0o600).open(&self.path)?);
+        }
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line)?;
+        }
+        self.len += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// `audit.log` for 0, `audit.log.<n>` otherwise.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    match n {
+        0 => path.to_path_buf(),
+        n => PathBuf::from(format!("{}.{}", path.display(), n)),
+    }
+}
+
+/// Entries in the file, oldest first; lines that don't parse are skipped.
+fn read_entries(path: &Path) -> Vec<AuditEntry> {
+    let Ok(file) = File::open(path) else { return Vec::new() };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
@@ -0,0 +1,96 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use log::{info, error};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{UnixListener, UnixStream};
+use wc_protocol::{framing, ClientMessage};
+use crate::device_registry::DeviceRegistry;
+use crate::event_handler::EventHandler;
+use crate::server::ConnectionRegistry;
+use crate::session_state::Session;
+
+/// `$XDG_RUNTIME_DIR/wayland-connect.sock`. There is no fallback: other
+/// locations are not guaranteed to be private to the user.
+pub fn socket_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")?;
+    Some(PathBuf::from(dir).join(wc_core::constants::CONTROL_SOCKET_NAME))
+}
+
+/// Local control channel for the desktop dashboard.
+///
+/// Speaks the same length-prefixed messages as the TLS port, without TLS.
+/// Only processes of the user running the backend get in (checked with
+/// SO_PEERCRED), and only these sessions may register as a dashboard and
+/// send management commands.
+pub struct ControlSocket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    pub async fn bind(path: PathBuf) -> anyhow::Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(&path).await.is_ok() {
+                anyhow::bail!("{:?} is in use by another backend", path);
+            }
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Self { listener, path })
+    }
+
+    pub async fn run(self, handler: Arc<EventHandler>, registry: Arc<ConnectionRegistry>, devices: DeviceRegistry) {
+        info!("🔒 Local control socket listening on {:?}", self.path);
+        let uid = unsafe { libc::getuid() };
+        let next_id = AtomicU64::new(0);
+
+        loop {
+            let stream = match self.listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("❌ Control socket accept failed: {}", e);
+                    continue;
+                }
+            };
+            match stream.peer_cred() {
+                Ok(cred) if cred.uid() == uid => {}
+                Ok(cred) => {
+                    error!("⛔ Control socket connection from uid {} refused", cred.uid());
+                    continue;
+                }
+                Err(e) => {
+                    error!("⛔ Control socket peer credentials unavailable: {}", e);
+                    continue;
+                }
+            }
+
+            let addr = format!("unix:{}", next_id.fetch_add(1, Ordering::Relaxed));
+            let session = Arc::new(Session::local(addr, devices.clone()));
+            tokio::spawn(serve(stream, session, handler.clone(), registry.clone()));
+        }
+    }
+}
+
+async fn serve(stream: UnixStream, session: Arc<Session>, handler: Arc<EventHandler>, registry: Arc<ConnectionRegistry>) {
+    let (mut reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(64);
+    info!("🔌 Local control connection {}", session.addr);
+    registry.add(session.clone(), tx.clone());
+
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if writer.write_all(&msg).await.is_err() { break; }
+        }
+    });
+
+    while let Ok(payload) = framing::read_frame(&mut reader).await {
+        if let Ok(message) = framing::decode::<ClientMessage>(&payload) {
+            if handler.handle_event(message, &session, &tx).await { break; }
+        }
+    }
+    handler.release_held_input(&session).await;
+    registry.remove(&session.addr);
+}
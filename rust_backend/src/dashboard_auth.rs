@@ -0,0 +1,40 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use log::{info, warn};
+use rand::Rng;
+
+/// Secret a connection has to present in `RegisterDashboard`.
+///
+/// It lives in the config dir with mode 0600, so only processes of the same
+/// user can read it; being able to reach the port is not enough to manage
+/// devices.
+pub fn load_or_create_token(config_dir: &Path) -> anyhow::Result<String> {
+    let path = config_dir.join(wc_core::constants::DASHBOARD_TOKEN_FILE);
+
+    if let Ok(token) = fs::read_to_string(&path) {
+        let token = token.trim().to_string();
+        if !token.is_empty() {
+            let mode = fs::metadata(&path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                warn!("⚠️ {:?} was readable by other users; restricting it to 0600", path);
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            }
+            return Ok(token);
+        }
+        fs::remove_file(&path)?;
+    }
+
+    let token: String = rand::thread_rng().gen::<[u8; 32]>().iter().map(|b| format!("{:02x}", b)).collect();
+    let mut file = OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+    file.write_all(token.as_bytes())?;
+    info!("🔑 Created dashboard token at {:?}", path);
+    Ok(token)
+}
+
+/// Compares without stopping at the first difference.
+pub fn token_matches(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected.bytes().zip(presented.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
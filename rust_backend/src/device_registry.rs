@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use log::{debug, error};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::time::Instant;
+use wc_protocol::{DeviceInfo, Permission};
+use crate::touch::ScreenArea;
+
+/// How long the registry waits for more changes before writing `devices.json`.
+const SAVE_DELAY: Duration = Duration::from_millis(250);
+const DEVICES_FILE: &str = "devices.json";
+
+#[derive(Debug, Clone)]
+pub struct PendingMirror {
+    pub device_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub monitor: i32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct AppState {
+    pub devices: HashMap<String, DeviceInfo>,
+    #[serde(default)]
+    pub media_playing: bool,
+    #[serde(default)]
+    pub zoom_enabled: bool,
+    #[serde(default = "default_true")]
+    pub auto_connect: bool,
+    /// Only devices proving a pairing code may become trusted.
+    #[serde(default)]
+    pub require_pairing_code: bool,
+    #[serde(skip)]
+    pub pending_mirror: Option<PendingMirror>,
+    #[serde(skip)]
+    pub mirroring_device: Option<String>,
+    /// Connection (`ip:port`) that asked for each pending device's approval;
+    /// only it receives the client certificate
+    #[serde(skip)]
+    pub pairing_connections: HashMap<String, String>,
+    /// Where the mirrored monitor sits, for `ScreenTouch`
+    #[serde(skip)]
+    pub mirrored_area: Option<ScreenArea>,
+}
+
+fn default_true() -> bool { true }
+
+impl AppState {
+    pub fn load(config_dir: &Path) -> Self {
+        let file_path = config_dir.join(DEVICES_FILE);
+
+        if let Ok(content) = std::fs::read_to_string(file_path) {
+            if let Ok(mut state) = serde_json::from_str::<AppState>(&content) {
+                state.mirroring_device = None;
+                // Trusted before client certificates existed: anyone could
+                // claim their id, so they have to be approved again
+                for device in state.devices.values_mut() {
+                    if device.status == "Trusted" && device.cert_fingerprint.is_none() {
+                        device.status = "Pending".to_string();
+                    }
+                }
+                return state;
+            }
+        }
+        AppState { devices: HashMap::new(), media_playing: false, zoom_enabled: false, auto_connect: true, require_pairing_code: false, pending_mirror: None, mirroring_device: None, pairing_connections: HashMap::new(), mirrored_area: None }
+    }
+
+    pub fn is_trusted(&self, id: &str) -> bool {
+        self.devices.get(id).is_some_and(|d| d.status == "Trusted")
+    }
+
+    /// Whether `id` is trusted *and* holds `permission`.
+    pub fn has_permission(&self, id: &str, permission: Permission) -> bool {
+        self.devices.get(id)
+            .is_some_and(|d| d.status == "Trusted" && d.permissions.contains(&permission))
+    }
+
+    fn settings_differ(&self, other: &AppState) -> bool {
+        self.zoom_enabled != other.zoom_enabled
+            || self.auto_connect != other.auto_connect
+            || self.require_pairing_code != other.require_pairing_code
+    }
+}
+
+/// What changed in the registry, published after every command.
+#[derive(Debug, Clone)]
+pub enum RegistryEvent {
+    DeviceChanged(DeviceInfo),
+    DeviceRemoved(String),
+    SettingsChanged,
+    MirroringStarted(String),
+    MirroringStopped(String),
+}
+
+type Command = Box<dyn FnOnce(&mut AppState) + Send>;
+/// A state to write, and who waits for it to be on disk.
+type SaveRequest = (Arc<AppState>, Option<oneshot::Sender<()>>);
+
+/// Owner of the device list and settings.
+///
+/// A single task holds the `AppState`; everything else mutates it by
+/// sending commands and reads the latest snapshot, which is republished
+/// after every command. Nobody waits on a lock or on disk: changes are
+/// written to `devices.json` shortly after they settle, one save at a time
+/// by a separate writer task.
+#[derive(Clone)]
+pub struct DeviceRegistry {
+    commands: mpsc::UnboundedSender<Command>,
+    snapshot: watch::Receiver<Arc<AppState>>,
+    events: broadcast::Sender<RegistryEvent>,
+    saves: mpsc::UnboundedSender<SaveRequest>,
+}
+
+impl DeviceRegistry {
+    /// Starts the registry task; changes are saved to `devices.json` in `config_dir`.
+    pub fn spawn(state: AppState, config_dir: &Path) -> Self {
+        let (commands, rx) = mpsc::unbounded_channel();
+        let (snapshot_tx, snapshot) = watch::channel(Arc::new(state.clone()));
+        let (events, _) = broadcast::channel(256);
+        let (saves, save_rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_saves(save_rx, config_dir.join(DEVICES_FILE)));
+        tokio::spawn(run(state, rx, snapshot_tx, events.clone(), saves.clone()));
+        Self { commands, snapshot, events, saves }
+    }
+
+    /// Latest published state. Cheap; never blocks on the registry task.
+    pub fn snapshot(&self) -> Arc<AppState> {
+        self.snapshot.borrow().clone()
+    }
+
+    pub fn device(&self, id: &str) -> Option<DeviceInfo> {
+        self.snapshot().devices.get(id).cloned()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RegistryEvent> {
+        self.events.subscribe()
+    }
+
+    /// Runs `f` on the registry task and returns its result once the new
+    /// snapshot is visible.
+    pub async fn update<R: Send + 'static>(&self, f: impl FnOnce(&mut AppState) -> R + Send + 'static) -> R {
+        let (tx, rx) = oneshot::channel();
+        let command: Command = Box::new(move |state| {
+            let _ = tx.send(f(state));
+        });
+        if self.commands.send(command).is_err() {
+            panic!("device registry task is gone");
+        }
+        rx.await.expect("device registry task is gone")
+    }
+
+    /// Like `update`, for callers that don't need to wait (notification
+    /// threads, for instance).
+    pub fn submit(&self, f: impl FnOnce(&mut AppState) + Send + 'static) {
+        let _ = self.commands.send(Box::new(f));
+    }
+
+    /// Sets the status of a known device; `false` if there is none.
+    pub async fn set_status(&self, id: &str, status: &str) -> bool {
+        let (id, status) = (id.to_string(), status.to_string());
+        self.update(move |state| match state.devices.get_mut(&id) {
+            Some(dev) => {
+                dev.status = status;
+                true
+            }
+            None => false,
+        }).await
+    }
+
+    pub async fn remove(&self, id: &str) -> Option<DeviceInfo> {
+        let id = id.to_string();
+        self.update(move |state| state.devices.remove(&id)).await
+    }
+
+    pub async fn set_mirroring(&self, id: Option<String>) {
+        self.update(move |state| {
+            if id.is_none() {
+                state.mirrored_area = None;
+            }
+            state.mirroring_device = id;
+        }).await
+    }
+
+    /// Writes whatever is still waiting for the save delay.
+    pub async fn flush(&self) {
+        let (done, saved) = oneshot::channel();
+        if self.saves.send((self.snapshot(), Some(done))).is_ok() {
+            let _ = saved.await;
+        }
+    }
+}
+
+async fn run(
+    mut state: AppState,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    snapshot: watch::Sender<Arc<AppState>>,
+    events: broadcast::Sender<RegistryEvent>,
+    saves: mpsc::UnboundedSender<SaveRequest>,
+) {
+    let mut save_at: Option<Instant> = None;
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => {
+                let Some(command) = command else { break };
+                command(&mut state);
+                // Publish the snapshot first so subscribers reacting to an
+                // event already read the state it describes.
+                let before = snapshot.send_replace(Arc::new(state.clone()));
+                if publish_changes(&before, &state, &events) {
+                    save_at.get_or_insert_with(|| Instant::now() + SAVE_DELAY);
+                }
+            }
+            _ = tokio::time::sleep_until(save_at.unwrap_or_else(Instant::now)), if save_at.is_some() => {
+                save_at = None;
+                let _ = saves.send((Arc::new(state.clone()), None));
+            }
+        }
+    }
+
+    if save_at.is_some() {
+        let _ = saves.send((Arc::new(state), None));
+    }
+}
+
+/// Writes queued states in order, so an older snapshot never lands on top
+/// of a newer one and two saves never share the temporary file.
+async fn write_saves(mut saves: mpsc::UnboundedReceiver<SaveRequest>, file_path: PathBuf) {
+    let file_path = Arc::new(file_path);
+    while let Some((state, done)) = saves.recv().await {
+        let file_path = file_path.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || save(&state, &file_path)).await {
+            error!("❌ Failed to save devices: {}", e);
+        }
+        if let Some(done) = done {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Emits an event per difference; returns whether anything persisted changed.
+fn publish_changes(before: &AppState, after: &AppState, events: &broadcast::Sender<RegistryEvent>) -> bool {
+    let mut persist = false;
+
+    for (id, device) in &after.devices {
+        if before.devices.get(id) != Some(device) {
+            let _ = events.send(RegistryEvent::DeviceChanged(device.clone()));
+            persist = true;
+        }
+    }
+    for id in before.devices.keys().filter(|id| !after.devices.contains_key(*id)) {
+        let _ = events.send(RegistryEvent::DeviceRemoved(id.clone()));
+        persist = true;
+    }
+    if before.settings_differ(after) {
+        let _ = events.send(RegistryEvent::SettingsChanged);
+        persist = true;
+    }
+    if before.mirroring_device != after.mirroring_device {
+        if let Some(id) = &before.mirroring_device {
+            let _ = events.send(RegistryEvent::MirroringStopped(id.clone()));
+        }
+        if let Some(id) = &after.mirroring_device {
+            let _ = events.send(RegistryEvent::MirroringStarted(id.clone()));
+        }
+    }
+    persist
+}
+
+/// Writes through a temporary file so a crash never leaves a torn `devices.json`.
+fn save(state: &AppState, file_path: &Path) {
+    let tmp_path = file_path.with_extension("json.tmp");
+
+    let result = serde_json::to_string_pretty(state)
+        .map_err(std::io::Error::from)
+        .and_then(|json| std::fs::write(&tmp_path, json))
+        .and_then(|_| std::fs::rename(&tmp_path, file_path));
+    match result {
+        Ok(()) => debug!("💾 Saved {} device(s)", state.devices.len()),
+        Err(e) => error!("❌ Failed to save devices to {:?}: {}", file_path, e),
+    }
+}
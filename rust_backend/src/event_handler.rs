@@ -1,143 +1,173 @@
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use crate::protocol::{InputEvent, ControlResponse, DeviceInfo};
+use wc_protocol::{framing, AuditAction, ClientMessage, InputEvent, ControlResponse, DashboardEvent, DeviceInfo, ErrorCode};
 use crate::adapter::InputAdapter;
-use crate::media_manager::MediaManager;
 use crate::pointer_manager::PointerManager;
-use crate::app_manager::AppManager;
 use crate::screen_streamer::ScreenStreamer;
-use crate::session_state::STATE;
-use log::{info, error, debug};
-use notify_rust::Notification;
+use crate::services::{Notice, Services};
+use crate::session_state::Session;
+use crate::device_registry::{AppState, DeviceRegistry, PendingMirror};
+use crate::pairing::PairingCheck;
+use crate::touch::ScreenArea;
+use log::{info, error, debug, warn};
 use base64::Engine;
 
+/// Why a client request failed, reported back as `Ack` or `Error`.
+#[derive(Debug)]
+pub struct RequestError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl RequestError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    fn unknown_device(id: &str) -> Self {
+        Self::new(ErrorCode::UnknownDevice, format!("No device with id '{}'", id))
+    }
+
+    fn not_dashboard() -> Self {
+        Self::new(ErrorCode::NotDashboard, "Only an authenticated dashboard can do this")
+    }
+}
+
+type RequestResult = Result<(), RequestError>;
+
+/// Device id for device events sent by a dashboard (e.g. `wcctl pointer test`).
+const LOCAL_DEVICE_ID: &str = "local";
+
+/// Keys a `KeyCombo` may hold down besides the one it taps.
+const MAX_COMBO_MODIFIERS: usize = 8;
+/// Longest text a single `TypeText` may enter.
+const MAX_TYPE_TEXT_CHARS: usize = 4096;
+
+/// Canonical name of `key` (see `crate::keymap`), so held keys compare equal
+/// however the device spelled them.
+fn known_key(key: &str) -> Result<String, RequestError> {
+    crate::keymap::lookup(key)
+        .map(|k| format!("{:?}", k))
+        .ok_or_else(|| RequestError::new(ErrorCode::InvalidRequest, format!("Unknown key '{}'", key)))
+}
+
 #[derive(Clone)]
 pub struct EventHandler {
     pub adapter: Arc<dyn InputAdapter + Send + Sync>,
-    pub media_manager: Arc<MediaManager>,
+    pub services: Services,
     pub pointer_manager: Arc<PointerManager>,
     pub screen_streamer: Arc<ScreenStreamer>,
     pub registry: Arc<crate::server::ConnectionRegistry>,
-    pub audio_analyzer: Arc<crate::audio_analyzer::AudioAnalyzer>,
-    pub fingerprint: String,
+    pub devices: DeviceRegistry,
+    pub identity: Arc<crate::tls_utils::ServerIdentity>,
+    pub server_name: String,
+    /// Secret from `dashboard.token`, checked by `RegisterDashboard`
+    pub dashboard_token: String,
+    pub ca: Arc<crate::tls_utils::DeviceCa>,
+    pub pairing: Arc<crate::pairing::PairingManager>,
+    pub limits: Arc<crate::limits::ConnectionLimits>,
+    pub audit: Arc<crate::audit::AuditLog>,
+    pub port: u16,
 }
 
 impl EventHandler {
-    pub async fn handle_event(&self, event: InputEvent, device_ip: &str, connection_addr: &str, tx_h: &Sender<Vec<u8>>) -> bool {
+    pub async fn handle_event(&self, message: ClientMessage, session: &Session, tx_h: &Sender<Vec<u8>>) -> bool {
+        let result = self.dispatch(message.event, session, tx_h).await;
+        let response = match (message.request_id, result) {
+            (Some(request_id), Ok(())) => ControlResponse::Ack { request_id, ok: true, error_code: None, message: None },
+            (Some(request_id), Err(e)) => ControlResponse::Ack {
+                request_id,
+                ok: false,
+                error_code: Some(e.code),
+                message: Some(e.message),
+            },
+            // Without a request id only failures are worth a reply
+            (None, Err(e)) => ControlResponse::Error { code: e.code, message: e.message },
+            (None, Ok(())) => return false,
+        };
+        self.send_packet(&response, tx_h).await;
+        false
+    }
+
+    async fn dispatch(&self, event: InputEvent, session: &Session, tx_h: &Sender<Vec<u8>>) -> RequestResult {
+        if event.is_management() && !self.registry.is_dashboard(&session.addr) {
+            error!("⛔ Management command from {} without dashboard authentication", session.addr);
+            return Err(RequestError::not_dashboard());
+        }
+
         match event {
-            InputEvent::PairRequest { device_name, id, version, auto_reconnect } => {
-                self.handle_pair_request(device_name, id, version, auto_reconnect, device_ip, connection_addr, tx_h).await;
-                false
+            InputEvent::PairRequest { device_name, id, version, auto_reconnect, pairing_proof, capabilities } => {
+                self.handle_pair_request(device_name, id, version, auto_reconnect, pairing_proof, capabilities, session, tx_h).await;
+                Ok(())
             },
             InputEvent::GetStatus => {
-                let (devices, zoom_enabled) = {
-                    let state = STATE.lock().unwrap();
-                    let mut devices: Vec<DeviceInfo> = state.devices.values().cloned().collect();
-                    let mirroring_id = state.mirroring_device.clone();
-                    for d in devices.iter_mut() {
-                        d.is_mirroring = Some(d.id.clone()) == mirroring_id; 
-                    }
-                    (devices, state.zoom_enabled)
-                };
-                let response = ControlResponse::StatusResponse { devices, zoom_enabled };
+                let response = status_response(&self.devices.snapshot());
                 self.send_packet(&response, tx_h).await;
-                false
+                Ok(())
             },
             InputEvent::ApproveDevice { id } => {
-                self.handle_approve_device(id).await;
-                false
+                self.handle_approve_device(id, "dashboard").await
             },
             InputEvent::RejectDevice { id } => {
                 info!("Rejecting device: {}", id);
-                let (ip, _was_active) = {
-                    let mut state = STATE.lock().unwrap();
-                    let dev = state.devices.get_mut(&id);
-                    let ip = dev.as_ref().map(|d| d.ip.clone());
-                    if let Some(d) = dev {
-                        d.status = "Declined".to_string();
-                        state.save();
-                    }
-                    (ip, true) // Assume it might be active
-                };
+                let known = self.devices.set_status(&id, "Declined").await;
+                let ip = self.devices.device(&id).map(|d| d.ip).unwrap_or_default();
+                self.limits.record_decline(&id, &ip);
+                self.audit.record(AuditAction::RejectDevice, &id, Some(&ip), if known { "Declined" } else { "UnknownDevice" }, Some("dashboard".to_string()));
                 
-                if let Some(ip) = ip {
-                     self.registry.send_to(&ip, &ControlResponse::SecurityUpdate { status: "Declined".to_string() }).await;
-                }
-                {
-                    let mut state = STATE.lock().unwrap();
-                    state.mirroring_device = None;
+                if known {
+                     self.registry.send_to(&id, &ControlResponse::SecurityUpdate { status: "Declined".to_string() }).await;
                 }
+                self.devices.set_mirroring(None).await;
                 self.screen_streamer.stop(); // Stop mirroring on rejection
-                false 
+                if known { Ok(()) } else { Err(RequestError::unknown_device(&id)) }
             },
             InputEvent::BlockDevice { id } => {
-                self.handle_block_device(id).await;
-                false
+                self.handle_block_device(id).await
             },
             InputEvent::UnblockDevice { id } => {
                 info!("Unblocking device: {}", id);
-                let mut state = STATE.lock().unwrap();
-                state.devices.remove(&id);
-                state.save();
-                false
+                self.devices.remove(&id).await.ok_or_else(|| RequestError::unknown_device(&id))?;
+                Ok(())
             },
             InputEvent::Discovery {} => {
                 self.handle_discovery(tx_h).await;
-                false
+                Ok(())
             },
             InputEvent::SetZoomEnabled { enabled } => {
-                let mut state = STATE.lock().unwrap();
-                state.zoom_enabled = enabled;
-                state.save();
-                self.pointer_manager.set_zoom_enabled(device_ip, enabled);
-                false
+                self.devices.update(move |state| state.zoom_enabled = enabled).await;
+                self.pointer_manager.set_zoom_enabled(enabled);
+                Ok(())
             },
             InputEvent::SetAutoConnect { enabled } => {
-                let mut state = STATE.lock().unwrap();
-                state.auto_connect = enabled;
-                state.save();
-                false
+                self.devices.update(move |state| state.auto_connect = enabled).await;
+                Ok(())
             },
             InputEvent::SetDeviceAutoReconnect { id, enabled } => {
-                let mut state = STATE.lock().unwrap();
-                if let Some(dev) = state.devices.get_mut(&id) {
-                    dev.auto_reconnect = enabled;
-                    state.save();
+                // Devices may change their own setting; anything else is management
+                if session.device_id().as_deref() != Some(id.as_str()) && !self.registry.is_dashboard(&session.addr) {
+                    return Err(RequestError::not_dashboard());
                 }
-                false
+                self.set_auto_reconnect(id, enabled).await
             },
             InputEvent::AutoReconnectResponse { id, accepted } => {
                 info!("PC response for auto-reconnect device {}: {}", id, accepted);
-                let mut state = STATE.lock().unwrap();
-                if let Some(dev) = state.devices.get_mut(&id) {
-                    dev.auto_reconnect = accepted;
-                    state.save();
-                }
-                false
+                self.set_auto_reconnect(id, accepted).await
             },
             InputEvent::PCStopMirroring { id } => {
                 info!("🛑 PC requested stop mirroring for device: {}", id);
-                let ip = {
-                    let state = STATE.lock().unwrap();
-                    state.devices.get(&id).map(|d| d.ip.clone())
-                };
-                if let Some(ip) = ip {
-                    self.registry.send_to(&ip, &ControlResponse::StopMirroring).await;
-                }
-                {
-                    let mut state = STATE.lock().unwrap();
-                    state.mirroring_device = None;
-                }
+                self.registry.send_to(&id, &ControlResponse::StopMirroring).await;
+                self.devices.set_mirroring(None).await;
                 self.screen_streamer.stop();
-                false
+                Ok(())
             },
             InputEvent::RequestAutoReconnect { id } => {
+                if session.device_id().as_deref() != Some(id.as_str()) {
+                    return Err(RequestError::new(ErrorCode::InvalidRequest, "A device can only ask for itself"));
+                }
                 info!("Device {} requested auto-reconnect", id);
-                let (device_id, device_name) = {
-                    let state = STATE.lock().unwrap();
-                    let dev = state.devices.get(&id);
-                    (id.clone(), dev.map(|d| d.name.clone()).unwrap_or_else(|| "Unknown Device".to_string()))
-                };
+                let device_id = id.clone();
+                let device_name = self.devices.device(&id).map(|d| d.name).unwrap_or_else(|| "Unknown Device".to_string());
 
                 // Broadcast to dashboards instead of just a system notification
                 let req = ControlResponse::AutoReconnectRequest { 
@@ -147,73 +177,222 @@ impl EventHandler {
                 self.registry.broadcast_to_dashboard(&req).await;
 
                 // Also keep notification for redundancy/utility
-                let _ = Notification::new()
-                    .appname("Wayland Connect")
-                    .summary("Auto-Reconnect Request")
-                    .body(&format!("'{}' wants to enable auto-reconnect.", device_name))
-                    .icon("wayland-connect")
-                    .show();
+                if self.limits.allow_notification() {
+                    self.services.notifier.notify(Notice::new(
+                        "Auto-Reconnect Request",
+                        format!("'{}' wants to enable auto-reconnect.", device_name),
+                        "wayland-connect",
+                    ));
+                }
                 
-                false
+                Ok(())
             },
             InputEvent::MirrorResponse { device_id, accepted } => {
-                self.handle_mirror_response(device_id, accepted).await;
-                false
+                self.handle_mirror_response(device_id, accepted, "dashboard").await;
+                Ok(())
+            },
+            InputEvent::StartPairing => {
+                let (code, ttl) = self.pairing.start();
+                let qr_payload = crate::pairing::qr_payload(&self.server_name, self.port, &self.identity.fingerprint(), &code);
+                let response = ControlResponse::PairingCode { code, qr_payload, expires_in_secs: ttl.as_secs() };
+                self.send_packet(&response, tx_h).await;
+                Ok(())
+            },
+            InputEvent::CancelPairing => {
+                self.pairing.cancel();
+                Ok(())
+            },
+            InputEvent::SetRequirePairingCode { enabled } => {
+                self.devices.update(move |state| state.require_pairing_code = enabled).await;
+                Ok(())
+            },
+            InputEvent::GetDevicePermissions { id } => {
+                let permissions = self.devices.device(&id).map(|d| d.permissions);
+                let permissions = permissions.ok_or_else(|| RequestError::unknown_device(&id))?;
+                self.send_packet(&ControlResponse::DevicePermissions { id, permissions }, tx_h).await;
+                Ok(())
+            },
+            InputEvent::SetDevicePermissions { id, permissions } => {
+                info!("🔑 Setting permissions for {}: {:?}", id, permissions);
+                let id_c = id.clone();
+                let permissions = self.devices.update(move |state| {
+                    state.devices.get_mut(&id_c).map(|d| {
+                        d.permissions = permissions;
+                        d.permissions.clone()
+                    })
+                }).await;
+                let permissions = permissions.ok_or_else(|| RequestError::unknown_device(&id))?;
+                self.send_packet(&ControlResponse::DevicePermissions { id, permissions }, tx_h).await;
+                Ok(())
+            },
+            InputEvent::RotateCertificate { grace_secs } => {
+                let old_fingerprint = self.identity.fingerprint();
+                let rotation = self.identity.rotate(std::time::Duration::from_secs(grace_secs));
+                let outcome = if rotation.is_ok() { "Scheduled" } else { "Failed" };
+                self.audit.record(AuditAction::RotateCertificate, LOCAL_DEVICE_ID, Some(&session.ip), outcome, Some(format!("from {} with {}s grace", old_fingerprint, grace_secs)));
+                let rotation = rotation.map_err(|e| RequestError::new(ErrorCode::RotationFailed, e.to_string()))?;
+
+                let trusted: Vec<String> = self.devices.snapshot().devices.values()
+                    .filter(|d| d.status == "Trusted")
+                    .map(|d| d.id.clone())
+                    .collect();
+                for id in trusted {
+                    self.registry.send_to(&id, &rotation).await;
+                }
+                self.send_packet(&rotation, tx_h).await;
+                Ok(())
+            },
+            InputEvent::GetAuditLog { before, limit } => {
+                let audit = self.audit.clone();
+                let (entries, next_before) = tokio::task::spawn_blocking(move || audit.page(before, limit as usize))
+                    .await
+                    .unwrap_or_default();
+                self.send_packet(&ControlResponse::AuditLog { entries, next_before }, tx_h).await;
+                Ok(())
             },
-            InputEvent::RegisterDashboard => {
-                info!("📡 Received RegisterDashboard from {}", connection_addr);
-                self.registry.mark_as_dashboard(connection_addr);
+            InputEvent::OpenMediaChannel => {
+                if !session.is_trusted() {
+                    return Err(RequestError::new(ErrorCode::NotTrusted, "Device is not trusted"));
+                }
+                if !session.supports(wc_protocol::capability::TRANSPORT_QUIC) {
+                    return Err(RequestError::new(ErrorCode::InvalidRequest, "transport.quic was not negotiated"));
+                }
+                let response = ControlResponse::MediaChannel {
+                    port: wc_core::constants::MEDIA_PORT,
+                    token: session.media_token.clone(),
+                };
+                self.send_packet(&response, tx_h).await;
+                Ok(())
+            },
+            InputEvent::RegisterDashboard { token } => {
+                info!("📡 Received RegisterDashboard from {}", session.addr);
+                if !session.local {
+                    error!("⛔ Rejected dashboard registration from {}: not on the control socket", session.addr);
+                    self.send_packet(&ControlResponse::RegisterResponse { success: false }, tx_h).await;
+                    return Err(RequestError::new(ErrorCode::NotDashboard, "Dashboards connect over the local control socket"));
+                }
+                if !crate::dashboard_auth::token_matches(&self.dashboard_token, &token) {
+                    error!("⛔ Rejected dashboard registration from {}: wrong token", session.addr);
+                    self.send_packet(&ControlResponse::RegisterResponse { success: false }, tx_h).await;
+                    return Err(RequestError::new(ErrorCode::PermissionDenied, "Invalid dashboard token"));
+                }
+                self.registry.mark_as_dashboard(&session.addr);
                 self.send_packet(&ControlResponse::RegisterResponse { success: true }, tx_h).await;
-                false
+                Ok(())
+            },
+            InputEvent::SubscribeEvents => {
+                if !self.registry.subscribe_dashboard(&session.addr) {
+                    return Err(RequestError::not_dashboard());
+                }
+                // Starting point; events from here on describe changes to it
+                self.send_packet(&status_response(&self.devices.snapshot()), tx_h).await;
+                Ok(())
             },
+            _ if self.registry.is_dashboard(&session.addr) => {
+                // Dashboards are local to the user, so they act with every scope
+                self.handle_trusted_event(event, session, tx_h, LOCAL_DEVICE_ID).await
+            }
             _ => {
-                let is_trusted = {
-                    let state = STATE.lock().unwrap();
-                    state.devices.values().any(|d| d.ip == device_ip && d.status == "Trusted")
+                let Some(device_id) = session.device_id().filter(|_| session.is_trusted()) else {
+                    return Err(RequestError::new(ErrorCode::NotTrusted, "Device is not trusted"));
                 };
-
-                if is_trusted {
-                    self.handle_trusted_event(event, tx_h, device_ip).await;
+                if let Some(permission) = event.required_permission() {
+                    if !session.has_permission(permission) {
+                        debug!("⛔ {} lacks '{:?}' permission", device_id, permission);
+                        match &event {
+                            InputEvent::LaunchApp { command } => {
+                                self.audit.record(AuditAction::LaunchApp, &device_id, Some(&session.ip), "PermissionDenied", Some(command.clone()));
+                            },
+                            InputEvent::StartMirroring { width, height, fps, monitor } => {
+                                self.audit.record(AuditAction::StartMirroring, &device_id, Some(&session.ip), "PermissionDenied", Some(format!("{}x{} @ {}fps on monitor {}", width, height, fps, monitor)));
+                            },
+                            _ => {},
+                        }
+                        return Err(RequestError::new(
+                            ErrorCode::PermissionDenied,
+                            format!("Device is not allowed to use {:?}", permission),
+                        ));
+                    }
                 }
-                false
+                self.handle_trusted_event(event, session, tx_h, &device_id).await
             }
         }
     }
 
     #[allow(clippy::too_many_arguments)]
-    async fn handle_pair_request(&self, device_name: String, id: String, version: String, auto_reconnect_req: Option<bool>, device_ip: &str, _connection_addr: &str, tx_h: &Sender<Vec<u8>>) {
+    async fn handle_pair_request(&self, device_name: String, id: String, version: String, auto_reconnect_req: Option<bool>, pairing_proof: Option<String>, capabilities: Option<Vec<String>>, session: &Session, tx_h: &Sender<Vec<u8>>) {
         info!("Pair request from: {} ({}) [v{}]", device_name, id, version);
+        if !self.limits.allow_pair_request(&session.ip) {
+            self.reply_to_pair(&id, "RateLimited", session, tx_h).await;
+            return;
+        }
+
+        // Mixed versions are fine: the capability lists decide what gets sent.
+        // Clients without a list keep the legacy feature set.
+        if let Some(capabilities) = capabilities {
+            let unknown: Vec<&String> = capabilities.iter()
+                .filter(|c| !wc_protocol::capability::KNOWN.contains(&c.as_str()))
+                .collect();
+            if !unknown.is_empty() {
+                debug!("Ignoring unknown capabilities from {}: {:?}", session.addr, unknown);
+            }
+            let negotiated = session.negotiate_capabilities(&capabilities);
+            debug!("🤝 Negotiated capabilities with {}: {:?}", session.addr, negotiated);
+        }
         let server_version = env!("CARGO_PKG_VERSION");
-        
-        // Simple version check (major.minor)
-        if !version.starts_with(server_version.split('.').next().unwrap_or("1")) && !version.is_empty() {
-             info!("⚠️ Version mismatch: Client v{}, Server v{}", version, server_version);
-             // We can construct a map or a custom struct for pair_response error, 
-             // but protocol.rs InputEvent doesn't have a generic "Response" enum for everything.
-             // We will stick to the loosely typed "Map" logic via serde_json Value serialized to msgpack 
-             // OR define proper response types. 
-             // For simplicity in this refactor, let's use a temporary struct or dynamic value.
-             // But rmp_serde works best with structs.
-             // Let's rely on loose typing for responses if the client supports it, or define structs.
-             // Defined `InputEvent` has no `PairResponse`. Protocol definition is needed.
-             // Client expects: {"type": "pair_response", "data": ...}
-              let resp = ControlResponse::PairResponse {
-                 status: "VersionMismatch".to_string(),
-                 server_version: server_version.to_string(),
-                 server_name: format!("Update required! Server is v{}.", server_version),
-                 fingerprint: Some(self.fingerprint.clone()),
-              };
-             self.send_packet(&resp, tx_h).await;
-             return; 
+        if !version.is_empty() && version.split('.').next() != server_version.split('.').next() {
+            info!("⚠️ Version mismatch: Client v{}, Server v{}. Continuing with negotiated capabilities", version, server_version);
+        }
+
+        // Devices that were issued a certificate have to present it: the id in
+        // the request proves nothing on its own.
+        let issued_fingerprint = self.devices.device(&id).and_then(|d| d.cert_fingerprint);
+        let certificate_missing = issued_fingerprint.as_ref()
+            .is_some_and(|fp| session.cert_fingerprint.as_ref() != Some(fp));
+        if certificate_missing {
+            error!("⚠️ Connection {} claimed {} without its client certificate", session.addr, id);
+            self.reply_to_pair(&id, "CertificateRequired", session, tx_h).await;
+            return;
+        }
+
+        // A connection speaks for a single device for its whole lifetime, and
+        // an id without a certificate for a single connection.
+        if !self.registry.bind_device(session, &id, issued_fingerprint.is_some()) {
+            error!("⚠️ Connection {} can't pair as {} (bound to {:?}, or the id is in use)", session.addr, id, session.device_id());
+            self.reply_to_pair(&id, "Rejected", session, tx_h).await;
+            return;
         }
+        self.registry.publish(DashboardEvent::DeviceConnected {
+            id: id.clone(),
+            name: device_name.clone(),
+            ip: session.ip.clone(),
+        }).await;
 
-        let (status, should_notify) = {
-            let mut state = STATE.lock().unwrap();
-            let ip_blocked_or_declined = state.devices.values()
-                .find(|d| d.ip == device_ip && (d.status == "Blocked" || d.status == "Declined"))
+        if let Some(proof) = pairing_proof {
+            let rejection = match self.pairing.verify(&session.ip, &id, &self.identity.fingerprint(), &proof) {
+                PairingCheck::Accepted => None,
+                PairingCheck::Invalid => Some("InvalidCode"),
+                PairingCheck::NoActiveCode => Some("CodeExpired"),
+                PairingCheck::RateLimited => Some("RateLimited"),
+            };
+            match rejection {
+                Some(status) => self.reply_to_pair(&id, status, session, tx_h).await,
+                None => self.handle_code_pairing(device_name, id, auto_reconnect_req, session, tx_h).await,
+            }
+            return;
+        }
+
+        let (id_c, device_name_c, ip, addr) = (id.clone(), device_name.clone(), session.ip.clone(), session.addr.clone());
+        let cooling_down = self.limits.recently_declined(&id, &session.ip);
+        let (status, should_notify) = self.devices.update(move |state| {
+            let (id, device_name) = (id_c, device_name_c);
+            let pairing_id = id.clone();
+            let asked_here = state.pairing_connections.get(&id) == Some(&addr);
+            let blocked_or_declined = state.devices.get(&id)
+                .filter(|d| d.status == "Blocked" || d.status == "Declined")
                 .map(|d| d.status.clone());
 
-            if let Some(blocked_status) = ip_blocked_or_declined {
+            let result = if let Some(blocked_status) = blocked_or_declined {
                 (blocked_status, false)
             } else if let Some(existing) = state.devices.get_mut(&id) {
                 let status;
@@ -223,145 +402,267 @@ impl EventHandler {
                     if let Some(req) = auto_reconnect_req {
                         existing.auto_reconnect = req;
                     }
+                    existing.ip = ip;
 
                     if existing.status == "Trusted" && !existing.auto_reconnect {
                         info!("Auto-reconnect disabled for {}: Requiring re-approval", id);
+                        // Trust is checked against the stored status, so the
+                        // device really has to go back to Pending here.
+                        existing.status = "Pending".to_string();
+                        status = "Pending".to_string();
+                        should_notify = true;
+                    } else if existing.status == "Trusted" && existing.cert_fingerprint.is_none() {
+                        // Its certificate never reached it; the id alone proves nothing
+                        info!("{} has no client certificate: Requiring re-approval", id);
+                        existing.status = "Pending".to_string();
                         status = "Pending".to_string();
                         should_notify = true;
+                    } else if existing.status == "Pending" {
+                        // Asked again from a new connection: whoever asked before
+                        // is gone, or binding would have failed
+                        status = existing.status.clone();
+                        should_notify = !asked_here;
                     } else {
                         status = existing.status.clone();
                         should_notify = false;
                     }
                 }
 
-                if auto_reconnect_req.is_some() {
-                    state.save();
+                if state.require_pairing_code && status == "Pending" {
+                    ("CodeRequired".to_string(), false)
+                } else {
+                    (status, should_notify)
                 }
-                (status, should_notify)
+            } else if state.require_pairing_code {
+                ("CodeRequired".to_string(), false)
+            } else if cooling_down {
+                ("Declined".to_string(), false)
             } else {
                 state.devices.insert(id.clone(), DeviceInfo {
-                    id: id.clone(),
-                    name: device_name.clone(),
+                    id,
+                    name: device_name,
                     status: "Pending".to_string(),
-                    ip: device_ip.to_string(),
+                    ip,
                     auto_reconnect: auto_reconnect_req.unwrap_or(false),
                     is_mirroring: false,
+                    cert_fingerprint: None,
+                    permissions: wc_protocol::default_permissions(),
                 });
                 ("Pending".to_string(), true)
+            };
+
+            // This connection waits for the approval, so it gets the certificate
+            if result.0 == "Pending" {
+                state.pairing_connections.insert(pairing_id, addr);
             }
-        };
+            result
+        }).await;
 
-        if should_notify {
-            let device_name_c = device_name.clone();
-            let id_c = id.clone();
-            std::thread::spawn(move || {
-                let notification = Notification::new()
-                    .appname("Wayland Connect")
-                    .summary("New Connection Request")
-                    .body(&format!("'{}' wants to connect.", device_name_c))
-                    .icon("network-wireless")
-                    .action("approve", "Approve")
-                    .action("decline", "Decline")
-                    .show();
-                    
-                if let Ok(handle) = notification {
-                    handle.wait_for_action(move |action| {
-                         let mut state = STATE.lock().unwrap();
-                         if let Some(dev) = state.devices.get_mut(&id_c) {
-                            match action {
-                                "approve" => dev.status = "Trusted".to_string(),
-                                "decline" => dev.status = "Declined".to_string(),
-                                _ => {}
+        if should_notify && !self.limits.allow_notification() {
+            warn!("🔕 Too many prompts, {} waits for the dashboard", id);
+        } else if should_notify {
+            let answer = self.services.notifier.ask(Notice::new(
+                "New Connection Request",
+                format!("'{}' wants to connect.", device_name),
+                "network-wireless",
+            ));
+            let (id_c, ip_c) = (id.clone(), session.ip.clone());
+            let handler = self.clone();
+            tokio::spawn(async move {
+                match answer.await {
+                    Ok(true) => {
+                        let _ = handler.handle_approve_device(id_c, "notification").await;
+                    }
+                    Ok(false) => {
+                        handler.limits.record_decline(&id_c, &ip_c);
+                        handler.audit.record(AuditAction::RejectDevice, &id_c, Some(&ip_c), "Declined", Some("notification".to_string()));
+                        handler.devices.submit(move |state| {
+                            if let Some(dev) = state.devices.get_mut(&id_c) {
+                                dev.status = "Declined".to_string();
                             }
-                            state.save();
-                         }
-                    });
+                        });
+                    }
+                    // Dismissed without an answer
+                    Err(_) => {}
                 }
             });
         }
 
-        let response = ControlResponse::PairResponse {
-            status,
-            server_version: server_version.to_string(),
-            server_name: get_server_name(),
-            fingerprint: Some(self.fingerprint.clone()),
+        self.reply_to_pair(&id, &status, session, tx_h).await;
+
+        // Devices that were away when a rotation started hear about it now
+        if status == "Trusted" {
+            if let Some(rotation) = self.identity.pending_rotation() {
+                self.registry.send_to(&id, &rotation).await;
+            }
+        }
+    }
+
+    /// Completes a pairing proven with the out-of-band code: the device is
+    /// trusted immediately, no approval prompt needed.
+    async fn handle_code_pairing(&self, device_name: String, id: String, auto_reconnect_req: Option<bool>, session: &Session, tx_h: &Sender<Vec<u8>>) {
+        let (id_c, device_name_c, ip) = (id.clone(), device_name.clone(), session.ip.clone());
+        let blocked = self.devices.update(move |state| {
+            let blocked = state.devices.get(&id_c).is_some_and(|d| d.status == "Blocked");
+            if !blocked {
+                let device = state.devices.entry(id_c.clone()).or_insert_with(|| DeviceInfo {
+                    id: id_c,
+                    name: device_name_c,
+                    status: "Pending".to_string(),
+                    ip: ip.clone(),
+                    auto_reconnect: false,
+                    is_mirroring: false,
+                    cert_fingerprint: None,
+                    permissions: wc_protocol::default_permissions(),
+                });
+                device.status = "Trusted".to_string();
+                device.ip = ip;
+                if let Some(req) = auto_reconnect_req {
+                    device.auto_reconnect = req;
+                }
+            }
+            blocked
+        }).await;
+
+        if blocked {
+            self.reply_to_pair(&id, "Blocked", session, tx_h).await;
+            return;
+        }
+
+        info!("🔢 {} ({}) paired with a verified code", device_name, id);
+        self.audit.record(AuditAction::PairRequest, &id, Some(&session.ip), "Trusted", Some("pairing code".to_string()));
+        self.send_packet(&self.pair_response("Trusted"), tx_h).await;
+        self.issue_client_certificate(&id, &session.addr).await;
+    }
+
+    async fn set_auto_reconnect(&self, id: String, enabled: bool) -> RequestResult {
+        let id_c = id.clone();
+        let known = self.devices.update(move |state| {
+            state.devices.get_mut(&id_c).map(|dev| dev.auto_reconnect = enabled).is_some()
+        }).await;
+        if known { Ok(()) } else { Err(RequestError::unknown_device(&id)) }
+    }
+
+    /// Answers a `PairRequest` and records the outcome in the audit log.
+    async fn reply_to_pair(&self, id: &str, status: &str, session: &Session, tx_h: &Sender<Vec<u8>>) {
+        self.audit.record(AuditAction::PairRequest, id, Some(&session.ip), status, None);
+        self.send_packet(&self.pair_response(status), tx_h).await;
+    }
+
+    fn pair_response(&self, status: &str) -> ControlResponse {
+        ControlResponse::PairResponse {
+            status: status.to_string(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            server_name: self.server_name.clone(),
+            fingerprint: Some(self.identity.fingerprint()),
+            capabilities: wc_protocol::capability::SERVER.iter().map(|c| c.to_string()).collect(),
+            protocol_version: wc_protocol::PROTOCOL_VERSION,
+        }
+    }
+
+    /// Signs a client certificate for `id`, records its fingerprint and hands
+    /// it to the connection at `addr`, the one that asked to pair. Nothing is
+    /// issued once that connection is gone. Replaces any earlier certificate.
+    async fn issue_client_certificate(&self, id: &str, addr: &str) {
+        let Some(name) = self.devices.device(id).map(|d| d.name) else { return };
+        let Some(tx) = self.registry.bound_connection(addr, id) else {
+            warn!("🪪 {} disconnected before its client certificate was issued", id);
+            return;
         };
-        self.send_packet(&response, tx_h).await;
+
+        let issued = match self.ca.issue(id, &name) {
+            Ok(issued) => issued,
+            Err(e) => {
+                error!("❌ Failed to issue client certificate for {}: {}", id, e);
+                return;
+            }
+        };
+
+        let (id_c, fingerprint) = (id.to_string(), issued.fingerprint.clone());
+        self.devices.update(move |state| {
+            if let Some(dev) = state.devices.get_mut(&id_c) {
+                dev.cert_fingerprint = Some(fingerprint);
+            }
+        }).await;
+        info!("🪪 Issued client certificate for {} ({})", id, issued.fingerprint);
+
+        let response = ControlResponse::ClientCertificate {
+            cert_pem: issued.cert_pem,
+            key_pem: issued.key_pem,
+            fingerprint: issued.fingerprint,
+        };
+        self.send_packet(&response, &tx).await;
     }
 
-    async fn handle_approve_device(&self, id: String) {
+    /// `via` says who approved, for the audit log.
+    async fn handle_approve_device(&self, id: String, via: &str) -> RequestResult {
         info!("Approving device: {}", id);
-        let device_name = {
-            let mut state = STATE.lock().unwrap();
-            if let Some(dev) = state.devices.get_mut(&id) {
+        let id_c = id.clone();
+        let (device, requester) = self.devices.update(move |state| {
+            let requester = state.pairing_connections.remove(&id_c);
+            let device = state.devices.get_mut(&id_c).map(|dev| {
                 dev.status = "Trusted".to_string();
-                let name = dev.name.clone();
-                state.save();
-                Some(name)
-            } else {
-                None
-            }
-        };
+                (dev.name.clone(), dev.ip.clone())
+            });
+            (device, requester)
+        }).await;
+        let outcome = if device.is_some() { "Trusted" } else { "UnknownDevice" };
+        self.audit.record(AuditAction::ApproveDevice, &id, device.as_ref().map(|(_, ip)| ip.as_str()), outcome, Some(via.to_string()));
 
-        if let Some(name) = device_name {
-            let _ = Notification::new()
-                .appname("Wayland Connect")
-                .summary("Device Paired")
-                .body(&format!("{} is now connected.", name))
-                .icon("security-high")
-                .show();
+        if let Some((name, _)) = device {
+            self.services.notifier.notify(Notice::new("Device Paired", format!("{} is now connected.", name), "security-high"));
 
             // Notify the device itself
-            let ip = {
-                let state = STATE.lock().unwrap();
-                state.devices.get(&id).map(|d| d.ip.clone())
-            };
-            if let Some(ip) = ip {
-                self.registry.send_to(&ip, &ControlResponse::SecurityUpdate { status: "Trusted".to_string() }).await;
+            self.registry.send_to(&id, &ControlResponse::SecurityUpdate { status: "Trusted".to_string() }).await;
+            if let Some(addr) = requester {
+                self.issue_client_certificate(&id, &addr).await;
             }
+            Ok(())
+        } else {
+            Err(RequestError::unknown_device(&id))
         }
     }
 
-    async fn handle_block_device(&self, id: String) {
+    async fn handle_block_device(&self, id: String) -> RequestResult {
         info!("Blocking device: {}", id);
-        let ip = {
-            let mut state = STATE.lock().unwrap();
-            if let Some(dev) = state.devices.get_mut(&id) {
+        let id_c = id.clone();
+        let ip = self.devices.update(move |state| {
+            // Blocking also ends any mirroring session
+            state.mirroring_device = None;
+            state.devices.get_mut(&id_c).map(|dev| {
                 dev.status = "Blocked".to_string();
-                let ip = dev.ip.clone();
-                state.save();
-                Some(ip)
-            } else {
-                None
-            }
-        };
+                // Revoke the device certificate along with the trust
+                dev.cert_fingerprint = None;
+                dev.ip.clone()
+            })
+        }).await;
+        let known = ip.is_some();
+        self.audit.record(AuditAction::BlockDevice, &id, ip.as_deref(), if known { "Blocked" } else { "UnknownDevice" }, Some("dashboard".to_string()));
 
-        if let Some(ip) = ip {
+        if known {
             // Proactively notify the device so it can show the blocked UI immediately
-            self.registry.send_to(&ip, &ControlResponse::SecurityUpdate { status: "Blocked".to_string() }).await;
-        }
-        {
-            let mut state = STATE.lock().unwrap();
-            state.mirroring_device = None;
+            self.registry.send_to(&id, &ControlResponse::SecurityUpdate { status: "Blocked".to_string() }).await;
         }
         self.screen_streamer.stop();
+        if known { Ok(()) } else { Err(RequestError::unknown_device(&id)) }
     }
 
     async fn handle_discovery(&self, tx_h: &Sender<Vec<u8>>) {
         let response = ControlResponse::DiscoveryResponse {
-            server_name: get_server_name(),
-            fingerprint: Some(self.fingerprint.clone()),
+            server_name: self.server_name.clone(),
+            fingerprint: Some(self.identity.fingerprint()),
         };
         self.send_packet(&response, tx_h).await;
     }
 
-    async fn handle_trusted_event(&self, event: InputEvent, tx_h: &Sender<Vec<u8>>, device_ip: &str) {
+    async fn handle_trusted_event(&self, event: InputEvent, session: &Session, tx_h: &Sender<Vec<u8>>, device_id: &str) -> RequestResult {
         match event {
             InputEvent::MediaControl { action } => {
-                let _ = self.media_manager.send_command(&action).await;
+                self.services.media.send_command(&action).await
+                    .map_err(|e| RequestError::new(ErrorCode::MediaFailed, e.to_string()))?;
             },
             InputEvent::MediaGetStatus => {
-                let metadata = self.media_manager.get_current_player_metadata().await;
+                let metadata = self.services.media.current_metadata().await;
                 let response = ControlResponse::MediaStatus {
                     metadata,
                 };
@@ -369,234 +670,278 @@ impl EventHandler {
             },
             InputEvent::PointerData { active, mode, pitch, roll, size, color, zoom_scale, particle_type, stretch_factor, has_image, pulse_speed, pulse_intensity } => {
                 debug!("🖱️ Received PointerData: active={}, mode={}, pitch={}, roll={}, speed={}", active, mode, pitch, roll, pulse_speed);
-                self.pointer_manager.update(device_ip, active, mode, pitch, roll, size, color, zoom_scale, particle_type, stretch_factor, has_image, pulse_speed, pulse_intensity);
+                if self.pointer_manager.update(device_id, active, mode, pitch, roll, size, color, zoom_scale, particle_type, stretch_factor, has_image, pulse_speed, pulse_intensity) {
+                    self.registry.publish(DashboardEvent::PointerActive { device_id: device_id.to_string(), active }).await;
+                }
             },
             InputEvent::TestOverlaySequence => {
-                self.pointer_manager.run_test_sequence(device_ip);
+                self.pointer_manager.run_test_sequence(device_id);
             },
             InputEvent::PresentationControl { action } => {
                 let key = match action.as_str() {
                     "prev" => "PageUp",
                     "next" => "PageDown",
-                    _ => return,
+                    _ => return Err(RequestError::new(ErrorCode::InvalidRequest, format!("Unknown presentation action '{}'", action))),
+                };
+                self.send_input(session, InputEvent::KeyPress { key: key.to_string() }).await?;
+            },
+            InputEvent::KeyPress { key } => {
+                self.send_input(session, InputEvent::KeyPress { key: known_key(&key)? }).await?;
+            },
+            InputEvent::KeyDown { key } => {
+                let key = known_key(&key)?;
+                session.hold_key(&key);
+                self.send_input(session, InputEvent::KeyDown { key }).await?;
+            },
+            InputEvent::KeyUp { key } => {
+                let key = known_key(&key)?;
+                session.release_key(&key);
+                self.send_input(session, InputEvent::KeyUp { key }).await?;
+            },
+            InputEvent::KeyCombo { modifiers, key } => {
+                if modifiers.len() > MAX_COMBO_MODIFIERS {
+                    return Err(RequestError::new(ErrorCode::InvalidRequest, format!("At most {} modifiers per combo", MAX_COMBO_MODIFIERS)));
+                }
+                let modifiers = modifiers.iter().map(|m| known_key(m)).collect::<Result<Vec<_>, _>>()?;
+                self.send_input(session, InputEvent::KeyCombo { modifiers, key: known_key(&key)? }).await?;
+            },
+            InputEvent::TypeText { text } => {
+                if text.chars().count() > MAX_TYPE_TEXT_CHARS {
+                    return Err(RequestError::new(ErrorCode::InvalidRequest, format!("At most {} characters per TypeText", MAX_TYPE_TEXT_CHARS)));
+                }
+                self.send_input(session, InputEvent::TypeText { text }).await?;
+            },
+            InputEvent::TouchFrame { contacts } => {
+                if contacts.len() > crate::touch::MAX_CONTACTS {
+                    return Err(RequestError::new(ErrorCode::InvalidRequest, format!("At most {} touch contacts", crate::touch::MAX_CONTACTS)));
+                }
+                session.set_touching(!contacts.is_empty());
+                self.send_input(session, InputEvent::TouchFrame { contacts }).await?;
+            },
+            InputEvent::ScreenTouch { x, y, state } => {
+                if !matches!(state.as_str(), "down" | "move" | "up") {
+                    return Err(RequestError::new(ErrorCode::InvalidRequest, format!("Unknown touch state '{}'", state)));
+                }
+                let snapshot = self.devices.snapshot();
+                let area = match (&snapshot.mirroring_device, snapshot.mirrored_area) {
+                    (Some(mirroring), Some(area)) if mirroring == device_id => area,
+                    _ => return Err(RequestError::new(ErrorCode::InvalidRequest, "This device is not mirroring a screen")),
                 };
-                let _ = self.adapter.send_event(InputEvent::KeyPress { key: key.to_string() }).await;
+                session.set_screen_touching(state != "up");
+                let (x, y) = area.map(x, y);
+                self.send_input(session, InputEvent::ScreenTouch { x, y, state }).await?;
             },
             InputEvent::SetPointerMonitor { monitor } => {
-                self.pointer_manager.set_monitor(device_ip, monitor);
+                self.pointer_manager.set_monitor(device_id, monitor);
             },
             InputEvent::LaunchApp { command } => {
-                let apps = AppManager::get_installed_apps();
-                if apps.iter().any(|a| a.exec == command) {
-                    info!("🚀 Launching verified app: {}", command);
-                    let _ = std::process::Command::new("sh").arg("-c").arg(&command).spawn();
-                } else {
+                let apps = self.services.apps.installed_apps();
+                if !apps.iter().any(|a| a.exec == command) {
                     error!("⚠️ Blocked attempt to launch unverified command: {}", command);
+                    self.audit.record(AuditAction::LaunchApp, device_id, Some(&session.ip), "UnverifiedApp", Some(command.clone()));
+                    return Err(RequestError::new(ErrorCode::UnverifiedApp, format!("'{}' is not an installed application", command)));
                 }
+                info!("🚀 Launching verified app: {}", command);
+                let launched = std::process::Command::new("sh").arg("-c").arg(&command).spawn();
+                self.audit.record(AuditAction::LaunchApp, device_id, Some(&session.ip), if launched.is_ok() { "Launched" } else { "LaunchFailed" }, Some(command));
+                launched.map_err(|e| RequestError::new(ErrorCode::LaunchFailed, e.to_string()))?;
             },
             InputEvent::GetApps => {
-                let apps = AppManager::get_installed_apps();
+                let apps = self.services.apps.installed_apps();
                 let response = ControlResponse::AppsList { apps };
                 self.send_packet(&response, tx_h).await;
             },
             InputEvent::GetMonitors => {
-                let monitors = self.fetch_monitors();
+                let monitors = self.services.monitors.monitors();
                 let response = ControlResponse::MonitorsList { monitors };
                 self.send_packet(&response, tx_h).await;
             },
             InputEvent::StartMirroring { width, height, fps, monitor } => {
                 info!("🖥️ Start Mirroring requested (awaiting approval): {}x{} @ {}fps on monitor {}", width, height, fps, monitor);
                 
-                let (id, name) = {
-                    let mut state = STATE.lock().unwrap();
-                    let device = state.devices.values()
-                        .find(|d| d.ip == device_ip)
+                let device_id_c = device_id.to_string();
+                let (id, name) = self.devices.update(move |state| {
+                    let device = state.devices.get(&device_id_c)
                         .map(|d| (d.id.clone(), d.name.clone()))
-                        .unwrap_or_else(|| ("unknown".to_string(), "Unknown Device".to_string()));
+                        .unwrap_or_else(|| (device_id_c, "Unknown Device".to_string()));
                     
                     // Store request for later execution after approval
-                    state.pending_mirror = Some(crate::session_state::PendingMirror {
-                       _device_id: device.0.clone(),
+                    state.pending_mirror = Some(PendingMirror {
+                       device_id: device.0.clone(),
                        width, height, fps, monitor
                     });
                     device
-                };
+                }).await;
 
+                self.audit.record(AuditAction::StartMirroring, &id, Some(&session.ip), "Requested", Some(format!("{}x{} @ {}fps on monitor {}", width, height, fps, monitor)));
                 let req = ControlResponse::MirrorRequest { device_id: id.clone(), device_name: name.clone() };
                 println!("📡 Broadcasting MirrorRequest for {} to dashboards...", name);
                 self.registry.broadcast_to_dashboard(&req).await;
 
-                // Show System Notification
+                // Show System Notification, unless requests are coming too fast
+                if !self.limits.allow_notification() {
+                    warn!("🔕 Too many prompts, mirroring for {} waits for the dashboard", id);
+                    return Ok(());
+                }
+                let answer = self.services.notifier.ask(Notice::new(
+                    "Screen Share Request",
+                    format!("'{}' wants to mirror their screen.", name),
+                    "video-display",
+                ));
                 let handler = self.clone();
-                let device_id_c = id.clone();
-                let device_name_c = name.clone();
-                
-                std::thread::spawn(move || {
-                    let notification = Notification::new()
-                        .appname("Wayland Connect")
-                        .summary("Screen Share Request")
-                        .body(&format!("'{}' wants to mirror their screen.", device_name_c))
-                        .icon("video-display")
-                        .action("approve", "Approve")
-                        .action("decline", "Decline")
-                        .show();
-
-                     if let Ok(handle) = notification {
-                        handle.wait_for_action(move |action| {
-                             let accepted = match action {
-                                 "approve" => true,
-                                 "decline" => false,
-                                 _ => return, 
-                             };
-                             
-                             // Bridge to async using a cloned handler and tokio::spawn
-                             let handler_clone = handler.clone();
-                             tokio::spawn(async move {
-                                 handler_clone.handle_mirror_response(device_id_c.clone(), accepted).await;
-                             });
-                        });
-                     }
+                tokio::spawn(async move {
+                    if let Ok(accepted) = answer.await {
+                        handler.handle_mirror_response(id, accepted, "notification").await;
+                    }
                 });
             },
             InputEvent::StopMirroring => {
-                {
-                    let mut state = STATE.lock().unwrap();
-                    state.mirroring_device = None;
-                }
+                self.devices.set_mirroring(None).await;
                 self.screen_streamer.stop();
             },
             InputEvent::PointerImage { data } => {
-                self.handle_pointer_image(data).await;
+                let size = data.len();
+                let outcome = self.handle_pointer_image(data).await;
+                self.audit.record(AuditAction::PointerImage, device_id, Some(&session.ip), outcome, Some(format!("{} bytes of base64", size)));
             },
             InputEvent::Discovery {} => {
                 self.handle_discovery(tx_h).await;
             },
-            InputEvent::SetZoomEnabled { enabled } => {
-                let mut state = STATE.lock().unwrap();
-                state.zoom_enabled = enabled;
-                state.save();
-                self.pointer_manager.set_zoom_enabled(device_ip, enabled);
-            },
             InputEvent::SetAudioSensitivity { value } => {
-                self.audio_analyzer.set_sensitivity(value);
+                self.services.spectrum.set_sensitivity(value);
             },
             _ => {
-                let _ = self.adapter.send_event(event).await;
+                self.send_input(session, event).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_input(&self, session: &Session, event: InputEvent) -> RequestResult {
+        self.adapter.send_event(&session.addr, event).await
+            .map_err(|e| RequestError::new(ErrorCode::InputFailed, e.to_string()))
+    }
+
+    /// Lets go of keys and fingers the session left pressed, so a dropped
+    /// connection doesn't leave e.g. Ctrl held on the desktop.
+    pub async fn release_held_input(&self, session: &Session) {
+        for key in session.take_held_keys() {
+            debug!("⌨️ Releasing {} held by {}", key, session.addr);
+            if let Err(e) = self.adapter.send_event(&session.addr, InputEvent::KeyUp { key }).await {
+                error!("❌ Failed to release key: {}", e);
             }
         }
+        if session.take_touching() {
+            debug!("👆 Lifting fingers left down by {}", session.addr);
+            if let Err(e) = self.adapter.send_event(&session.addr, InputEvent::TouchFrame { contacts: Vec::new() }).await {
+                error!("❌ Failed to lift touch contacts: {}", e);
+            }
+        }
+        if session.take_screen_touching() {
+            debug!("👆 Lifting screen touch left down by {}", session.addr);
+            let up = InputEvent::ScreenTouch { x: 0.0, y: 0.0, state: "up".to_string() };
+            if let Err(e) = self.adapter.send_event(&session.addr, up).await {
+                error!("❌ Failed to lift screen touch: {}", e);
+            }
+        }
+        self.adapter.forget(&session.addr);
     }
 
-    async fn handle_pointer_image(&self, data: String) {
+    /// Returns the outcome for the audit log.
+    async fn handle_pointer_image(&self, data: String) -> &'static str {
         if data.is_empty() {
             let _ = std::fs::remove_file(wc_core::constants::POINTER_IMAGE_PATH);
             if let Ok(socket) = std::net::UdpSocket::bind("127.0.0.1:0") {
                 let _ = socket.send_to(b"CLEAR_IMAGE", wc_core::constants::POINTER_OVERLAY_ADDR);
             }
+            "Cleared"
         } else if let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data) {
             // Limit pointer image size to 1MB to prevent resource exhaustion
             if bytes.len() > 1024 * 1024 {
                 error!("⚠️ Pointer image too large: {} bytes", bytes.len());
-                return;
+                return "TooLarge";
             }
-            if let Ok(mut file) = std::fs::File::create(wc_core::constants::POINTER_IMAGE_PATH) {
-                let _ = std::io::Write::write_all(&mut file, &bytes);
-                if let Ok(socket) = std::net::UdpSocket::bind("127.0.0.1:0") {
-                    let _ = socket.send_to(b"RELOAD_IMAGE", wc_core::constants::POINTER_OVERLAY_ADDR);
-                }
+            let Ok(mut file) = std::fs::File::create(wc_core::constants::POINTER_IMAGE_PATH) else { return "WriteFailed" };
+            let _ = std::io::Write::write_all(&mut file, &bytes);
+            if let Ok(socket) = std::net::UdpSocket::bind("127.0.0.1:0") {
+                let _ = socket.send_to(b"RELOAD_IMAGE", wc_core::constants::POINTER_OVERLAY_ADDR);
             }
+            "Set"
+        } else {
+            "InvalidImage"
         }
     }
 
     async fn send_packet<T: serde::Serialize>(&self, val: &T, tx: &Sender<Vec<u8>>) {
-        if let Ok(bin) = rmp_serde::encode::to_vec_named(val) {
-            let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-            msg.extend_from_slice(&bin);
+        if let Ok(msg) = framing::encode(val) {
             let _ = tx.send(msg).await;
         } else {
             error!("Failed to serialize packet");
         }
     }
 
-    fn fetch_monitors(&self) -> Vec<crate::protocol::MonitorInfo> {
-        let output = std::process::Command::new("hyprctl")
-            .arg("monitors")
-            .arg("-j")
-            .output();
-
-        if let Ok(out) = output {
-            if let Ok(val) = serde_json::from_slice::<serde_json::Value>(&out.stdout) {
-                if let Some(arr) = val.as_array() {
-                    return arr.iter().map(|m| {
-                        crate::protocol::MonitorInfo {
-                            id: m.get("id").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
-                            name: m.get("name").and_then(|v| v.as_str()).unwrap_or("Unknown").to_string(),
-                            width: m.get("width").and_then(|v| v.as_i64()).unwrap_or(1920) as i32,
-                            height: m.get("height").and_then(|v| v.as_i64()).unwrap_or(1080) as i32,
-                            x: m.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
-                            y: m.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
-                            focused: m.get("focused").and_then(|v| v.as_bool()).unwrap_or(false),
-                        }
-                    }).collect();
-                }
-            }
-        }
-        vec![]
-    }
-
-    async fn handle_mirror_response(&self, device_id: String, accepted: bool) {
+    /// `via` says who answered, for the audit log.
+    async fn handle_mirror_response(&self, device_id: String, accepted: bool, via: &str) {
         info!("Mirror response for {}: {}", device_id, if accepted { "Accepted" } else { "Declined" });
         
-        let device_ip = {
-            let state = STATE.lock().unwrap();
-            state.devices.get(&device_id).map(|d| d.ip.clone())
+        let ip = self.devices.device(&device_id).map(|d| d.ip);
+        let audit = |outcome: &str| {
+            self.audit.record(AuditAction::MirrorResponse, &device_id, ip.as_deref(), outcome, Some(via.to_string()));
         };
 
-        if let Some(ip) = device_ip {
+        if ip.is_none() {
+            audit("UnknownDevice");
+        } else {
             if accepted {
-                // Retrieve pending request params
-                let pending = {
-                    let mut state = STATE.lock().unwrap();
-                    state.pending_mirror.take()
-                };
+                // Retrieve pending request params, but only if this device asked for them
+                let device_id_c = device_id.clone();
+                let pending = self.devices.update(move |state| {
+                    if state.pending_mirror.as_ref().is_some_and(|p| p.device_id == device_id_c) {
+                        state.pending_mirror.take()
+                    } else {
+                        None
+                    }
+                }).await;
 
+                audit(if pending.is_some() { "Accepted" } else { "NoPendingRequest" });
                 if let Some(p) = pending {
                     // Notify Android it's allowed
                     let status = ControlResponse::MirrorStatus { 
                         allowed: true, 
                         message: "Access granted by PC".to_string() 
                     };
-                    self.registry.send_to(&ip, &status).await;
+                    self.registry.send_to(&device_id, &status).await;
                     
                     // Start the actual stream with requested params
                     info!("🚀 Starting portal for {}x{} (Monitor {})", p.width, p.height, p.monitor);
-                    {
-                        let mut state = STATE.lock().unwrap();
+                    self.pointer_manager.set_monitor(&device_id, p.monitor);
+                    let area = ScreenArea::of_monitor(&self.services.monitors.monitors(), p.monitor);
+                    self.devices.update(move |state| {
                         state.mirroring_device = Some(device_id);
-                    }
-                    self.pointer_manager.set_monitor(&ip, p.monitor);
+                        state.mirrored_area = Some(area);
+                    }).await;
                     self.screen_streamer.start(p.width, p.height, p.fps, p.monitor);
                 }
             } else {
+                audit("Declined");
                 // Explicitly clear pending on rejection
-                {
-                    let mut state = STATE.lock().unwrap();
-                    state.pending_mirror = None;
-                }
+                self.devices.update(|state| state.pending_mirror = None).await;
                 
                 let status = ControlResponse::MirrorStatus { 
                     allowed: false, 
                     message: "Mirroring request declined by PC".to_string() 
                 };
-                self.registry.send_to(&ip, &status).await;
+                self.registry.send_to(&device_id, &status).await;
             }
         }
     }
 }
 
-fn get_server_name() -> String {
-    std::process::Command::new("hostname").output()
-        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
-        .ok()
-        .or_else(|| std::env::var("HOSTNAME").ok())
-        .unwrap_or_else(|| "Linux PC".to_string())
+/// Full device list for dashboards, with the mirroring flag filled in.
+pub fn status_response(state: &AppState) -> ControlResponse {
+    let mut devices: Vec<DeviceInfo> = state.devices.values().cloned().collect();
+    for d in devices.iter_mut() {
+        d.is_mirroring = state.mirroring_device.as_deref() == Some(d.id.as_str());
+    }
+    ControlResponse::StatusResponse { devices, zoom_enabled: state.zoom_enabled }
 }
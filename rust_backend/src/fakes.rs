@@ -0,0 +1,181 @@
+//! In-memory `Services` for running the server headless, e.g. in tests.
+//! Each fake records what the server asked of it and can be steered.
+
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use tokio::sync::oneshot;
+use wc_protocol::{AppInfo, MediaMetadata, MonitorInfo};
+use crate::services::{AppCatalog, MediaSource, MonitorProvider, Notice, Notifier, Services, SpectrumSource};
+
+/// One of each fake, kept around to steer them after the server took its `Services`.
+#[derive(Default, Clone)]
+pub struct Fakes {
+    pub media: Arc<FakeMedia>,
+    pub spectrum: Arc<FakeSpectrum>,
+    pub monitors: Arc<FakeMonitors>,
+    pub notifier: Arc<FakeNotifier>,
+    pub apps: Arc<FakeApps>,
+}
+
+impl Fakes {
+    pub fn services(&self) -> Services {
+        Services {
+            media: self.media.clone(),
+            spectrum: self.spectrum.clone(),
+            monitors: self.monitors.clone(),
+            notifier: self.notifier.clone(),
+            apps: self.apps.clone(),
+        }
+    }
+}
+
+/// A single player whose metadata is whatever was set last.
+#[derive(Default)]
+pub struct FakeMedia {
+    metadata: Mutex<Option<MediaMetadata>>,
+    commands: Mutex<Vec<String>>,
+}
+
+impl FakeMedia {
+    pub fn set_metadata(&self, metadata: Option<MediaMetadata>) {
+        *self.metadata.lock().unwrap() = metadata;
+    }
+
+    /// Commands received so far, oldest first.
+    pub fn commands(&self) -> Vec<String> {
+        self.commands.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MediaSource for FakeMedia {
+    async fn current_metadata(&self) -> Option<MediaMetadata> {
+        self.metadata.lock().unwrap().clone()
+    }
+
+    async fn send_command(&self, command: &str) -> anyhow::Result<()> {
+        if self.metadata.lock().unwrap().is_none() {
+            anyhow::bail!("No active media player");
+        }
+        self.commands.lock().unwrap().push(command.to_string());
+        Ok(())
+    }
+}
+
+pub struct FakeSpectrum {
+    levels: Mutex<Vec<f32>>,
+    sensitivity: Mutex<f32>,
+    target_app: Mutex<Option<String>>,
+}
+
+impl Default for FakeSpectrum {
+    fn default() -> Self {
+        Self {
+            levels: Mutex::new(vec![0.0; 7]),
+            sensitivity: Mutex::new(1.0),
+            target_app: Mutex::new(None),
+        }
+    }
+}
+
+impl FakeSpectrum {
+    pub fn set_levels(&self, levels: Vec<f32>) {
+        *self.levels.lock().unwrap() = levels;
+    }
+
+    pub fn sensitivity(&self) -> f32 {
+        *self.sensitivity.lock().unwrap()
+    }
+
+    pub fn target_app(&self) -> Option<String> {
+        self.target_app.lock().unwrap().clone()
+    }
+}
+
+impl SpectrumSource for FakeSpectrum {
+    fn levels(&self) -> Vec<f32> {
+        self.levels.lock().unwrap().clone()
+    }
+
+    fn set_sensitivity(&self, value: f32) {
+        *self.sensitivity.lock().unwrap() = value;
+    }
+
+    fn set_target_app(&self, app: Option<String>) {
+        *self.target_app.lock().unwrap() = app;
+    }
+}
+
+#[derive(Default)]
+pub struct FakeMonitors {
+    monitors: Mutex<Vec<MonitorInfo>>,
+}
+
+impl FakeMonitors {
+    pub fn set_monitors(&self, monitors: Vec<MonitorInfo>) {
+        *self.monitors.lock().unwrap() = monitors;
+    }
+}
+
+impl MonitorProvider for FakeMonitors {
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        self.monitors.lock().unwrap().clone()
+    }
+}
+
+#[derive(Default)]
+pub struct FakeApps {
+    apps: Mutex<Vec<AppInfo>>,
+}
+
+impl FakeApps {
+    pub fn set_apps(&self, apps: Vec<AppInfo>) {
+        *self.apps.lock().unwrap() = apps;
+    }
+}
+
+impl AppCatalog for FakeApps {
+    fn installed_apps(&self) -> Vec<AppInfo> {
+        self.apps.lock().unwrap().clone()
+    }
+}
+
+/// Keeps every notice; questions stay open until `answer` is called.
+#[derive(Default)]
+pub struct FakeNotifier {
+    shown: Mutex<Vec<Notice>>,
+    questions: Mutex<Vec<(Notice, oneshot::Sender<bool>)>>,
+}
+
+impl FakeNotifier {
+    /// Every notice shown, questions included, oldest first.
+    pub fn shown(&self) -> Vec<Notice> {
+        self.shown.lock().unwrap().clone()
+    }
+
+    /// Answers the oldest open question titled `summary`; `false` if there is none.
+    pub fn answer(&self, summary: &str, approve: bool) -> bool {
+        let mut questions = self.questions.lock().unwrap();
+        match questions.iter().position(|(notice, _)| notice.summary == summary) {
+            Some(i) => {
+                let (_, tx) = questions.remove(i);
+                let _ = tx.send(approve);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Notifier for FakeNotifier {
+    fn notify(&self, notice: Notice) {
+        self.shown.lock().unwrap().push(notice);
+    }
+
+    fn ask(&self, notice: Notice) -> oneshot::Receiver<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.shown.lock().unwrap().push(notice.clone());
+        self.questions.lock().unwrap().push((notice, tx));
+        rx
+    }
+}
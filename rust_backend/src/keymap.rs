@@ -0,0 +1,57 @@
+//! Key names accepted in keyboard events and the evdev keys they press.
+//!
+//! Names are matched case-insensitively, ignoring underscores and a `KEY_`
+//! prefix, so `"ctrl"`, `"Control_L"`, `"LeftCtrl"` and `"KEY_LEFTCTRL"` all
+//! mean the left Control key. Besides the aliases below, every keyboard key
+//! in `input_linux::Key` can be named as it is there (`"F13"`, `"VolumeUp"`,
+//! `"PlayPause"`, `"BrightnessDown"`, ...).
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use input_linux::Key;
+
+/// Names phones and X keysyms use that differ from the evdev ones.
+const ALIASES: &[(&str, Key)] = &[
+    ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3), ("4", Key::Num4), ("5", Key::Num5),
+    ("6", Key::Num6), ("7", Key::Num7), ("8", Key::Num8), ("9", Key::Num9), ("0", Key::Num0),
+    (" ", Key::Space), (",", Key::Comma), (".", Key::Dot), ("/", Key::Slash), (";", Key::Semicolon),
+    ("'", Key::Apostrophe), ("[", Key::LeftBrace), ("]", Key::RightBrace), ("-", Key::Minus),
+    ("=", Key::Equal), ("\\", Key::Backslash), ("`", Key::Grave),
+    ("escape", Key::Esc), ("return", Key::Enter), ("del", Key::Delete), ("ins", Key::Insert),
+    ("prior", Key::PageUp), ("next", Key::PageDown), ("printscreen", Key::Sysrq),
+    ("ctrl", Key::LeftCtrl), ("control", Key::LeftCtrl), ("controll", Key::LeftCtrl), ("controlr", Key::RightCtrl),
+    ("shift", Key::LeftShift), ("shiftl", Key::LeftShift), ("shiftr", Key::RightShift),
+    ("alt", Key::LeftAlt), ("altl", Key::LeftAlt), ("altr", Key::RightAlt), ("altgr", Key::RightAlt),
+    ("super", Key::LeftMeta), ("superl", Key::LeftMeta), ("superr", Key::RightMeta),
+    ("meta", Key::LeftMeta), ("metal", Key::LeftMeta), ("metar", Key::RightMeta), ("win", Key::LeftMeta),
+    ("mediaplaypause", Key::PlayPause), ("medianext", Key::NextSong), ("mediaprev", Key::PreviousSong),
+    ("mediastop", Key::StopCD), ("volumemute", Key::Mute),
+];
+
+fn normalize(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_prefix("key_").unwrap_or(&name);
+    if name.len() > 1 { name.replace('_', "") } else { name.to_string() }
+}
+
+fn table() -> &'static HashMap<String, Key> {
+    static TABLE: OnceLock<HashMap<String, Key>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table: HashMap<String, Key> = keyboard_keys()
+            .map(|key| (normalize(&format!("{:?}", key)), key))
+            .filter(|(name, _)| !name.starts_with("unknown"))
+            .collect();
+        table.extend(ALIASES.iter().map(|(name, key)| (name.to_string(), *key)));
+        table
+    })
+}
+
+/// The key called `name`, if there is one.
+pub fn lookup(name: &str) -> Option<Key> {
+    table().get(&normalize(name)).copied()
+}
+
+/// Every keyboard key, leaving out mouse, joystick and tablet buttons.
+pub fn keyboard_keys() -> impl Iterator<Item = Key> {
+    Key::iter().filter(|key| key.is_key() && *key != Key::Reserved)
+}
@@ -0,0 +1,26 @@
+//! The Wayland Connect backend as a library, so the server can be embedded
+//! with other input adapters and desktop services (see `tests/`).
+
+pub mod adapter;
+pub mod keymap;
+pub mod xkb;
+pub mod text_input;
+pub mod touch;
+pub mod server;
+pub mod media_manager;
+pub mod pointer_manager;
+pub mod audio_analyzer;
+pub mod app_manager;
+pub mod screen_streamer;
+pub mod session_state;
+pub mod device_registry;
+pub mod event_handler;
+pub mod tls_utils;
+pub mod pairing;
+pub mod limits;
+pub mod audit;
+pub mod dashboard_auth;
+pub mod control_socket;
+pub mod services;
+#[cfg(feature = "fakes")]
+pub mod fakes;
@@ -0,0 +1,145 @@
+use log::warn;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Open TLS and QUIC connections allowed from one address.
+pub const MAX_CONNECTIONS_PER_IP: usize = 8;
+/// Time a new connection gets to finish TLS and send its `PairRequest`, or
+/// a QUIC peer its media token.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pair requests per address: a burst of 5, then one every 10 seconds.
+const PAIR_REQUEST_BURST: u32 = 5;
+const PAIR_REQUEST_INTERVAL: Duration = Duration::from_secs(10);
+/// Desktop prompts peers can cause, across all addresses.
+const NOTIFICATION_BURST: u32 = 3;
+const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a declined device, and the address it asked from, can't ask again.
+const DECLINE_COOLDOWN: Duration = Duration::from_secs(600);
+/// Idle per-address state is dropped once this many addresses are tracked.
+const MAX_TRACKED: usize = 1024;
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(burst: u32) -> Self {
+        Self { tokens: burst as f64, updated: Instant::now() }
+    }
+
+    fn refill(&mut self, burst: u32, interval: Duration) {
+        let now = Instant::now();
+        let earned = now.duration_since(self.updated).as_secs_f64() / interval.as_secs_f64();
+        self.tokens = (self.tokens + earned).min(burst as f64);
+        self.updated = now;
+    }
+
+    fn take(&mut self, burst: u32, interval: Duration) -> bool {
+        self.refill(burst, interval);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// What a peer can make the server do before anyone trusted it: hold
+/// connections open, file pair requests and pop up desktop prompts.
+///
+/// Addresses are the normalized strings in `Session::ip`.
+pub struct ConnectionLimits {
+    connections: Arc<Mutex<HashMap<String, usize>>>,
+    pair_requests: Mutex<HashMap<String, TokenBucket>>,
+    notifications: Mutex<TokenBucket>,
+    /// Declined device ids and their addresses, with when it happened
+    declined: Mutex<HashMap<String, Instant>>,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectionLimits {
+    pub fn new() -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            pair_requests: Mutex::new(HashMap::new()),
+            notifications: Mutex::new(TokenBucket::full(NOTIFICATION_BURST)),
+            declined: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a new connection from `ip`, or `None` when it already has
+    /// `MAX_CONNECTIONS_PER_IP` open. The count drops with the permit.
+    pub fn admit(&self, ip: &str) -> Option<ConnectionPermit> {
+        let mut connections = self.connections.lock().unwrap();
+        let open = connections.entry(ip.to_string()).or_insert(0);
+        if *open >= MAX_CONNECTIONS_PER_IP {
+            warn!("⛔ Connection from {} refused: {} already open", ip, open);
+            return None;
+        }
+        *open += 1;
+        Some(ConnectionPermit { ip: ip.to_string(), connections: self.connections.clone() })
+    }
+
+    pub fn allow_pair_request(&self, ip: &str) -> bool {
+        let mut buckets = self.pair_requests.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED {
+            buckets.retain(|_, bucket| {
+                bucket.refill(PAIR_REQUEST_BURST, PAIR_REQUEST_INTERVAL);
+                bucket.tokens < PAIR_REQUEST_BURST as f64
+            });
+        }
+        let allowed = buckets.entry(ip.to_string())
+            .or_insert_with(|| TokenBucket::full(PAIR_REQUEST_BURST))
+            .take(PAIR_REQUEST_BURST, PAIR_REQUEST_INTERVAL);
+        if !allowed {
+            warn!("⛔ Pair request from {} rejected: too many requests", ip);
+        }
+        allowed
+    }
+
+    /// Whether a peer-triggered desktop prompt may be shown now.
+    pub fn allow_notification(&self) -> bool {
+        self.notifications.lock().unwrap().take(NOTIFICATION_BURST, NOTIFICATION_INTERVAL)
+    }
+
+    pub fn record_decline(&self, id: &str, ip: &str) {
+        let mut declined = self.declined.lock().unwrap();
+        declined.retain(|_, at| at.elapsed() < DECLINE_COOLDOWN);
+        let now = Instant::now();
+        declined.insert(id.to_string(), now);
+        if !ip.is_empty() {
+            declined.insert(ip.to_string(), now);
+        }
+    }
+
+    /// True while `id`, or any device from `ip`, was declined within the cooldown.
+    pub fn recently_declined(&self, id: &str, ip: &str) -> bool {
+        let declined = self.declined.lock().unwrap();
+        [id, ip].iter().any(|key| declined.get(*key).is_some_and(|at| at.elapsed() < DECLINE_COOLDOWN))
+    }
+}
+
+/// One counted connection; see `ConnectionLimits::admit`.
+pub struct ConnectionPermit {
+    ip: String,
+    connections: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(open) = connections.get_mut(&self.ip) {
+            *open -= 1;
+            if *open == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
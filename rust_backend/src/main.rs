@@ -1,20 +1,7 @@
-
-mod protocol;
-mod adapter;
-mod server;
-mod media_manager;
-mod pointer_manager;
-mod audio_analyzer;
-mod app_manager;
-mod screen_streamer;
-mod session_state;
-mod event_handler;
-mod tls_utils;
-
 use std::sync::Arc;
 
 use log::info;
-use crate::server::InputServer;
+use wayland_connect_backend::server::InputServer;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
 async fn main() -> anyhow::Result<()> {
@@ -26,7 +13,7 @@ async fn main() -> anyhow::Result<()> {
     info!("Starting WaylandConnect Backend (uinput mode)...");
 
     // 1. Initialize UInput Adapter (Create Virtual Mouse)
-    let uinput_adapter = crate::adapter::UInputAdapter::new()?;
+    let uinput_adapter = wayland_connect_backend::adapter::UInputAdapter::new()?;
     info!("Virtual mouse created successfully.");
 
     // 2. Wrap in Arc
@@ -41,7 +28,13 @@ async fn main() -> anyhow::Result<()> {
     };
 
     let server = InputServer::new(adapter_arc).await?;
-    server.run(port).await?;
+    tokio::select! {
+        result = server.run(port) => result?,
+        _ = tokio::signal::ctrl_c() => {
+            info!("Shutting down...");
+            server.devices().flush().await;
+        }
+    }
 
     Ok(())
 }
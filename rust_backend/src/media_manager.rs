@@ -1,6 +1,6 @@
 use zbus::{Connection, Proxy};
 use std::collections::HashMap;
-use crate::protocol::MediaMetadata;
+use wc_protocol::MediaMetadata;
 use log::{info, debug};
 
 pub struct MediaManager {
@@ -174,7 +174,7 @@ impl MediaManager {
             Some(p) => p,
             None => {
                 info!("No active media player to send command '{}' to", command);
-                return Ok(());
+                anyhow::bail!("No active media player");
             },
         };
 
@@ -237,9 +237,20 @@ impl MediaManager {
                     }
                 }
             }
-            _ => {}
+            _ => anyhow::bail!("Unknown media command '{}'", command),
         }
         
         Ok(())
     }
 }
+
+#[async_trait::async_trait]
+impl crate::services::MediaSource for MediaManager {
+    async fn current_metadata(&self) -> Option<MediaMetadata> {
+        self.get_current_player_metadata().await
+    }
+
+    async fn send_command(&self, command: &str) -> anyhow::Result<()> {
+        MediaManager::send_command(self, command).await
+    }
+}
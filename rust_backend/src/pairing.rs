@@ -0,0 +1,155 @@
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use rand::Rng;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a generated pairing code stays valid.
+const CODE_TTL: Duration = Duration::from_secs(120);
+/// Failed attempts allowed per address inside `FAILURE_WINDOW`.
+const MAX_FAILURES_PER_IP: u32 = 5;
+const FAILURE_WINDOW: Duration = Duration::from_secs(300);
+/// Failed attempts (from any address) after which the active code is burned.
+const MAX_FAILURES_PER_CODE: u32 = 10;
+
+struct ActiveCode {
+    code: String,
+    expires_at: Instant,
+    failures: u32,
+}
+
+struct FailureRecord {
+    count: u32,
+    window_start: Instant,
+}
+
+pub enum PairingCheck {
+    Accepted,
+    Invalid,
+    /// No code is active (never started, expired, burned or already used).
+    NoActiveCode,
+    RateLimited,
+}
+
+/// Out-of-band pairing with short-lived one-time codes.
+///
+/// The dashboard shows the code as digits and as a QR payload. The phone
+/// proves it saw the code by sending `HMAC-SHA256(code, device_id || fingerprint)`
+/// in its `PairRequest`, so the code itself never crosses the network and
+/// the proof is bound to the server certificate the phone pinned.
+pub struct PairingManager {
+    active: Mutex<Option<ActiveCode>>,
+    failures: Mutex<HashMap<String, FailureRecord>>,
+}
+
+impl Default for PairingManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PairingManager {
+    pub fn new() -> Self {
+        Self {
+            active: Mutex::new(None),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts a new pairing window, replacing any code still active.
+    pub fn start(&self) -> (String, Duration) {
+        let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+        *self.active.lock().unwrap() = Some(ActiveCode {
+            code: code.clone(),
+            expires_at: Instant::now() + CODE_TTL,
+            failures: 0,
+        });
+        info!("🔢 Pairing code generated (valid for {}s)", CODE_TTL.as_secs());
+        (code, CODE_TTL)
+    }
+
+    pub fn cancel(&self) {
+        *self.active.lock().unwrap() = None;
+    }
+
+    pub fn verify(&self, ip: &str, device_id: &str, fingerprint: &str, proof: &str) -> PairingCheck {
+        if self.is_rate_limited(ip) {
+            warn!("⛔ Pairing attempt from {} rejected: too many failures", ip);
+            return PairingCheck::RateLimited;
+        }
+
+        let mut active = self.active.lock().unwrap();
+        let Some(current) = active.as_mut().filter(|c| c.expires_at > Instant::now()) else {
+            *active = None;
+            return PairingCheck::NoActiveCode;
+        };
+
+        if proof_matches(&current.code, device_id, fingerprint, proof) {
+            // One-time: the code is gone as soon as one device used it
+            *active = None;
+            self.failures.lock().unwrap().remove(ip);
+            return PairingCheck::Accepted;
+        }
+
+        current.failures += 1;
+        if current.failures >= MAX_FAILURES_PER_CODE {
+            warn!("⛔ Pairing code burned after {} failed attempts", current.failures);
+            *active = None;
+        }
+        drop(active);
+        self.record_failure(ip);
+        PairingCheck::Invalid
+    }
+
+    fn is_rate_limited(&self, ip: &str) -> bool {
+        let mut failures = self.failures.lock().unwrap();
+        match failures.get(ip) {
+            Some(r) if r.window_start.elapsed() > FAILURE_WINDOW => {
+                failures.remove(ip);
+                false
+            }
+            Some(r) => r.count >= MAX_FAILURES_PER_IP,
+            None => false,
+        }
+    }
+
+    fn record_failure(&self, ip: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        let record = failures.entry(ip.to_string()).or_insert(FailureRecord {
+            count: 0,
+            window_start: Instant::now(),
+        });
+        record.count += 1;
+    }
+}
+
+/// Payload encoded in the pairing QR code.
+pub fn qr_payload(server_name: &str, port: u16, fingerprint: &str, code: &str) -> String {
+    format!(
+        "wayland-connect://pair?name={}&port={}&fp={}&code={}",
+        server_name.replace(['&', '=', ' '], "_"),
+        port,
+        fingerprint,
+        code
+    )
+}
+
+fn proof_matches(code: &str, device_id: &str, fingerprint: &str, proof: &str) -> bool {
+    let Some(proof) = decode_hex(proof) else { return false };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(code.as_bytes()) else { return false };
+    mac.update(device_id.as_bytes());
+    mac.update(fingerprint.as_bytes());
+    mac.verify_slice(&proof).is_ok()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -25,6 +25,8 @@ struct SinglePointerData {
 
 struct PointerManagerState {
     pointers: std::collections::HashMap<String, SinglePointerData>,
+    /// Given to pointers of devices that show up later
+    zoom_enabled: bool,
 }
 
 pub struct PointerManager {
@@ -32,6 +34,12 @@ pub struct PointerManager {
     input_socket: UdpSocket, 
 }
 
+impl Default for PointerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PointerManager {
     pub fn new() -> Self {
         // Bind to an ephemeral port
@@ -43,6 +51,7 @@ impl PointerManager {
 
         let state = Arc::new(Mutex::new(PointerManagerState {
             pointers: std::collections::HashMap::new(),
+            zoom_enabled: false,
         }));
 
         let state_clone = state.clone();
@@ -95,13 +104,15 @@ impl PointerManager {
         }
     }
 
+    /// Returns whether the pointer was switched on or off by this update.
     #[allow(clippy::too_many_arguments)]
-    pub fn update(&self, device_id: &str, active: bool, mode: i32, pitch: f32, roll: f32, size: f32, color: String, zoom: f32, particle: i32, stretch: f32, has_image: bool, pulse_speed: f32, pulse_intensity: f32) {
+    pub fn update(&self, device_id: &str, active: bool, mode: i32, pitch: f32, roll: f32, size: f32, color: String, zoom: f32, particle: i32, stretch: f32, has_image: bool, pulse_speed: f32, pulse_intensity: f32) -> bool {
         let mut state = match self.state.lock() {
             Ok(s) => s,
-            Err(_) => return,
+            Err(_) => return false,
         };
         
+        let zoom_enabled = state.zoom_enabled;
         let data = state.pointers.entry(device_id.to_string()).or_insert_with(|| SinglePointerData {
             active: false,
             mode: 0,
@@ -115,7 +126,7 @@ impl PointerManager {
             particle: 0,
             stretch: 1.0,
             has_image: false,
-            zoom_enabled: false,
+            zoom_enabled,
             pulse_speed: 1.0,
             pulse_intensity: 0.0,
         });
@@ -168,6 +179,8 @@ impl PointerManager {
                 let _ = self.input_socket.send_to(msg.as_bytes(), wc_core::constants::POINTER_OVERLAY_ADDR);
             }
         }
+
+        was_active != active
     }
 
     pub fn get_zoom_and_coords(&self, device_id: &str) -> (f32, f32, f32) {
@@ -189,9 +202,11 @@ impl PointerManager {
          let _ = self.input_socket.send_to(msg.as_bytes(), wc_core::constants::POINTER_OVERLAY_ADDR);
     }
 
-    pub fn set_zoom_enabled(&self, device_id: &str, enabled: bool) {
+    /// Zoom is a desktop-wide setting, so it applies to every device's pointer.
+    pub fn set_zoom_enabled(&self, enabled: bool) {
         let mut state = self.state.lock().unwrap();
-        if let Some(data) = state.pointers.get_mut(device_id) {
+        state.zoom_enabled = enabled;
+        for (device_id, data) in state.pointers.iter_mut() {
             data.zoom_enabled = enabled;
             if enabled {
                  let msg = format!("{}|START_CAPTURE", device_id);
@@ -28,6 +28,12 @@ pub struct ScreenStreamer {
     pointer_manager: Option<Arc<PointerManager>>,
 }
 
+impl Default for ScreenStreamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ScreenStreamer {
     pub fn new() -> Self {
         Self {
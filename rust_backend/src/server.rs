@@ -1,23 +1,42 @@
 use tokio::net::TcpListener;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncWriteExt;
 use tokio_rustls::TlsAcceptor;
-use crate::protocol::InputEvent;
+use wc_protocol::{capability, framing, ClientMessage, DashboardEvent, Permission};
 use crate::adapter::InputAdapter;
-use crate::media_manager::MediaManager;
 use crate::pointer_manager::PointerManager;
-use crate::audio_analyzer::AudioAnalyzer;
 use crate::screen_streamer::ScreenStreamer;
-use crate::session_state::STATE;
+use crate::services::Services;
+use crate::session_state::Session;
+use crate::device_registry::{AppState, DeviceRegistry, RegistryEvent};
 use crate::event_handler::EventHandler;
 use std::sync::Arc;
-use log::{info, error, debug};
+use log::{info, error, debug, warn};
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use tokio::sync::mpsc::Sender;
 use std::sync::Mutex as StdMutex;
+use wc_core::traits::TransportPeer;
+use wc_transport::server::QuicPeer;
 
+struct Connection {
+    tx: Sender<Vec<u8>>,
+    is_dashboard: bool,
+    /// Dashboard asked for the `DashboardEvent` stream
+    subscribed: bool,
+    session: Arc<Session>,
+}
+
+/// Live connections, keyed by connection address (`ip:port`) and indexed by
+/// the device id their session has been bound to.
 pub struct ConnectionRegistry {
-    #[allow(clippy::type_complexity)]
-    pub channels: StdMutex<HashMap<String, (Sender<Vec<u8>>, bool)>>,
+    channels: StdMutex<HashMap<String, Connection>>,
+}
+
+impl Default for ConnectionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConnectionRegistry {
@@ -25,37 +44,66 @@ impl ConnectionRegistry {
         Self { channels: StdMutex::new(HashMap::new()) }
     }
 
-    pub fn add(&self, addr: String, tx: Sender<Vec<u8>>) {
+    pub fn add(&self, session: Arc<Session>, tx: Sender<Vec<u8>>) {
         let mut channels = self.channels.lock().unwrap();
-        channels.insert(addr, (tx, false)); // Default to not a dashboard
+        channels.insert(session.addr.clone(), Connection { tx, is_dashboard: false, subscribed: false, session });
+    }
+
+    pub fn is_dashboard(&self, addr: &str) -> bool {
+        self.channels.lock().unwrap().get(addr).is_some_and(|c| c.is_dashboard)
     }
 
     pub fn mark_as_dashboard(&self, addr: &str) {
         let mut channels = self.channels.lock().unwrap();
         if let Some(entry) = channels.get_mut(addr) {
-            entry.1 = true;
+            entry.is_dashboard = true;
             info!("🖥️  Connection {} marked as DASHBOARD", addr);
         }
     }
 
+    /// Starts the event stream for a registered dashboard; `false` for
+    /// any other connection.
+    pub fn subscribe_dashboard(&self, addr: &str) -> bool {
+        let mut channels = self.channels.lock().unwrap();
+        match channels.get_mut(addr) {
+            Some(entry) if entry.is_dashboard => {
+                entry.subscribed = true;
+                info!("🖥️  Dashboard {} subscribed to events", addr);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Binds `session` to device `id` (see `Session::bind_device`). Without
+    /// a client certificate the id proves nothing, so an id that isn't
+    /// `certified` may only be held by one live connection at a time.
+    pub fn bind_device(&self, session: &Session, id: &str, certified: bool) -> bool {
+        let channels = self.channels.lock().unwrap();
+        let held_elsewhere = channels.values()
+            .any(|c| c.session.addr != session.addr && c.session.device_id().as_deref() == Some(id));
+        if held_elsewhere && !certified {
+            return false;
+        }
+        session.bind_device(id)
+    }
+
     pub fn remove(&self, addr: &str) {
         let mut channels = self.channels.lock().unwrap();
         channels.remove(addr);
     }
 
-    pub async fn broadcast_to_dashboard(&self, packet: &crate::protocol::ControlResponse) {
-        let bin = match rmp_serde::encode::to_vec_named(packet) {
-            Ok(b) => b,
+    pub async fn broadcast_to_dashboard(&self, packet: &wc_protocol::ControlResponse) {
+        let msg = match framing::encode(packet) {
+            Ok(msg) => msg,
             Err(_) => return,
         };
-        let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-        msg.extend_from_slice(&bin);
 
         let targets: Vec<Sender<Vec<u8>>> = {
             let channels = self.channels.lock().unwrap();
-            let found = channels.iter()
-                .filter(|(_, (_, is_dashboard))| *is_dashboard)
-                .map(|(_, (tx, _))| tx.clone())
+            let found = channels.values()
+                .filter(|c| c.is_dashboard)
+                .map(|c| c.tx.clone())
                 .collect::<Vec<_>>();
             
             info!("📡 Dashboard broadcast: Found {} registered dashboard(s) out of {} total connections. Active Addrs: {:?}", 
@@ -69,90 +117,280 @@ impl ConnectionRegistry {
         }
     }
 
-    pub async fn send_to(&self, key: &str, packet: &crate::protocol::ControlResponse) {
-        let bin = match rmp_serde::encode::to_vec_named(packet) {
-            Ok(b) => b,
+    /// Sends `event` to every subscribed dashboard.
+    pub async fn publish(&self, event: wc_protocol::DashboardEvent) {
+        self.send_to_dashboards(&wc_protocol::ControlResponse::DashboardEvent { event }, |c| c.subscribed).await;
+    }
+
+    /// Full status for dashboards that poll instead of subscribing.
+    pub async fn push_status(&self, status: &wc_protocol::ControlResponse) {
+        self.send_to_dashboards(status, |c| !c.subscribed).await;
+    }
+
+    async fn send_to_dashboards(&self, packet: &wc_protocol::ControlResponse, filter: impl Fn(&Connection) -> bool) {
+        let msg = match framing::encode(packet) {
+            Ok(msg) => msg,
             Err(_) => return,
         };
-        let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-        msg.extend_from_slice(&bin);
 
         let targets = {
             let channels = self.channels.lock().unwrap();
-            // Try direct match first (for connection_addr)
-            if let Some((tx, _)) = channels.get(key) {
-                vec![tx.clone()]
-            } else {
-                // Try matching by IP (key might be just IP)
-                channels.iter()
-                    .filter(|(addr, _)| addr.starts_with(key))
-                    .map(|(_, (tx, _))| tx.clone())
-                    .collect::<Vec<_>>()
-            }
+            channels.values()
+                .filter(|c| c.is_dashboard && filter(c))
+                .map(|c| c.tx.clone())
+                .collect::<Vec<_>>()
         };
 
         for tx in targets {
             let _ = tx.send(msg.clone()).await;
         }
     }
+
+    pub fn session_by_media_token(&self, token: &str) -> Option<Arc<Session>> {
+        let channels = self.channels.lock().unwrap();
+        channels.values()
+            .find(|c| c.session.media_token == token)
+            .map(|c| c.session.clone())
+    }
+
+    /// The sender of the connection at `addr`, while its session is still
+    /// bound to `device_id`.
+    pub fn bound_connection(&self, addr: &str, device_id: &str) -> Option<Sender<Vec<u8>>> {
+        let channels = self.channels.lock().unwrap();
+        channels.get(addr)
+            .filter(|c| c.session.device_id().as_deref() == Some(device_id))
+            .map(|c| c.tx.clone())
+    }
+
+    /// Sends `packet` to every connection bound to `device_id` that
+    /// negotiated the capability it needs.
+    pub async fn send_to(&self, device_id: &str, packet: &wc_protocol::ControlResponse) {
+        let msg = match framing::encode(packet) {
+            Ok(msg) => msg,
+            Err(_) => return,
+        };
+
+        let targets = {
+            let channels = self.channels.lock().unwrap();
+            channels.values()
+                .filter(|c| c.session.device_id().as_deref() == Some(device_id))
+                .filter(|c| packet.required_capability().is_none_or(|cap| c.session.supports(cap)))
+                .map(|c| c.tx.clone())
+                .collect::<Vec<_>>()
+        };
+
+        for tx in targets {
+            let _ = tx.send(msg.clone()).await;
+        }
+    }
+}
+
+/// Where the server keeps its state and which local endpoints it opens
+/// besides the TLS port.
+pub struct ServerOptions {
+    /// Certificates, `devices.json` and `dashboard.token`
+    pub config_dir: PathBuf,
+    /// Dashboard control socket; `None` disables it
+    pub control_socket: Option<PathBuf>,
+    /// QUIC media endpoint; `None` keeps all media on the TLS stream
+    pub media_addr: Option<SocketAddr>,
+    /// UDP discovery responder; `None` disables it
+    pub discovery_addr: Option<SocketAddr>,
+    /// Shown to phones in discovery and pairing
+    pub server_name: String,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            config_dir: crate::session_state::get_config_dir(),
+            control_socket: crate::control_socket::socket_path(),
+            media_addr: wc_core::constants::MEDIA_ADDR.parse().ok(),
+            discovery_addr: wc_core::constants::DISCOVERY_ADDR.parse().ok(),
+            server_name: get_server_host_name(),
+        }
+    }
 }
 
 pub struct InputServer {
     adapter: Arc<dyn InputAdapter + Send + Sync>,
-    media_manager: Arc<MediaManager>,
+    services: Services,
     pointer_manager: Arc<PointerManager>,
-    audio_analyzer: Arc<AudioAnalyzer>,
     screen_streamer: Arc<ScreenStreamer>,
     registry: Arc<ConnectionRegistry>,
+    devices: DeviceRegistry,
+    options: ServerOptions,
 }
 
 impl InputServer {
     pub async fn new(adapter: Arc<dyn InputAdapter + Send + Sync>) -> anyhow::Result<Self> {
-        let audio_analyzer = Arc::new(AudioAnalyzer::new());
-        audio_analyzer.start();
-        
+        Ok(Self::with_parts(adapter, Services::desktop().await?, ScreenStreamer::new(), ServerOptions::default()))
+    }
+
+    /// Builds a server around the given desktop services. With `crate::fakes`
+    /// (the `fakes` feature) it runs without a desktop session, as the tests do.
+    pub fn with_parts(
+        adapter: Arc<dyn InputAdapter + Send + Sync>,
+        services: Services,
+        mut screen_streamer: ScreenStreamer,
+        options: ServerOptions,
+    ) -> Self {
         let pointer_manager = Arc::new(PointerManager::new());
-        let mut screen_streamer = ScreenStreamer::new();
         screen_streamer.set_pointer_manager(pointer_manager.clone());
-        
-        Ok(Self { 
+        let devices = DeviceRegistry::spawn(AppState::load(&options.config_dir), &options.config_dir);
+
+        Self {
             adapter,
-            media_manager: Arc::new(MediaManager::new().await?),
+            services,
             pointer_manager,
-            audio_analyzer,
             screen_streamer: Arc::new(screen_streamer),
             registry: Arc::new(ConnectionRegistry::new()),
-        })
+            devices,
+            options,
+        }
+    }
+
+    pub fn devices(&self) -> &DeviceRegistry {
+        &self.devices
     }
 
     pub async fn run(&self, port: u16) -> anyhow::Result<()> {
-        let (tls_config, fingerprint) = crate::tls_utils::load_tls_config()?;
-        let acceptor = TlsAcceptor::from(tls_config);
-        
         let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-        info!("🔐 TLS Wayland Connect Server (Binary Mode) listening on 0.0.0.0:{}", port);
+        self.serve(listener).await
+    }
+
+    /// Serves clients accepted on `listener`; returns only if accepting fails.
+    pub async fn serve(&self, listener: TcpListener) -> anyhow::Result<()> {
+        let local_addr = listener.local_addr()?;
+        let port = local_addr.port();
+        let config_dir = &self.options.config_dir;
+        let (tls_config, identity, ca) = crate::tls_utils::load_tls_config(config_dir)?;
+        let acceptor = TlsAcceptor::from(tls_config.clone());
+        let dashboard_token = crate::dashboard_auth::load_or_create_token(config_dir)?;
+        
+        info!("🔐 TLS Wayland Connect Server (Binary Mode) listening on {}", local_addr);
+
+        // TLS and QUIC connections count against the same per-address cap
+        let limits = Arc::new(crate::limits::ConnectionLimits::new());
+
+        // QUIC media transport, sharing the certificate and client verifier
+        let media_addr = self.options.media_addr;
+        match media_addr.map(|addr| (addr, wc_transport::server::TransportServer::new(addr, (*tls_config).clone()))) {
+            None => info!("🎞️ QUIC media transport disabled"),
+            Some((_, Ok(transport))) => {
+                let (peer_tx, mut peer_rx) = tokio::sync::mpsc::channel(16);
+                let limits_q = limits.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = transport.run(peer_tx, |addr| limits_q.admit(&peer_ip(&addr))).await {
+                        error!("❌ QUIC transport stopped: {}", e);
+                    }
+                });
+                let registry = self.registry.clone();
+                tokio::spawn(async move {
+                    while let Some((peer, permit)) = peer_rx.recv().await {
+                        tokio::spawn(attach_media_peer(peer, permit, registry.clone()));
+                    }
+                });
+            }
+            Some((addr, Err(e))) => error!("❌ Failed to start QUIC transport on {}: {}", addr, e),
+        }
 
         let handler = Arc::new(EventHandler {
             adapter: self.adapter.clone(),
-            media_manager: self.media_manager.clone(),
+            services: self.services.clone(),
             pointer_manager: self.pointer_manager.clone(),
             screen_streamer: self.screen_streamer.clone(),
             registry: self.registry.clone(),
-            audio_analyzer: self.audio_analyzer.clone(),
-            fingerprint: fingerprint.clone(),
+            devices: self.devices.clone(),
+            identity: identity.clone(),
+            server_name: self.options.server_name.clone(),
+            dashboard_token,
+            ca,
+            pairing: Arc::new(crate::pairing::PairingManager::new()),
+            limits,
+            audit: Arc::new(crate::audit::AuditLog::open(&self.options.config_dir)),
+            port,
+        });
+
+        // Local control socket for the dashboard (management commands)
+        match self.options.control_socket.clone() {
+            Some(path) => match crate::control_socket::ControlSocket::bind(path).await {
+                Ok(control) => {
+                    tokio::spawn(control.run(handler.clone(), self.registry.clone(), self.devices.clone()));
+                }
+                Err(e) => error!("❌ Failed to open control socket: {}", e),
+            },
+            None => error!("❌ No control socket path (is XDG_RUNTIME_DIR set?); the dashboard control socket is disabled"),
+        }
+
+        // Keep dashboards in sync with every registry change
+        let registry_d = self.registry.clone();
+        let devices_d = self.devices.clone();
+        tokio::spawn(async move {
+            let mut events = devices_d.subscribe();
+            loop {
+                let event = match events.recv().await {
+                    Ok(RegistryEvent::DeviceChanged(device)) => Some(DashboardEvent::DeviceStatusChanged { device }),
+                    Ok(RegistryEvent::DeviceRemoved(id)) => Some(DashboardEvent::DeviceRemoved { id }),
+                    Ok(RegistryEvent::MirroringStarted(device_id)) => Some(DashboardEvent::MirroringStarted { device_id }),
+                    Ok(RegistryEvent::MirroringStopped(device_id)) => Some(DashboardEvent::MirroringStopped { device_id }),
+                    Ok(RegistryEvent::SettingsChanged) => None,
+                    // Missed some events; subscribers resync from a full status
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        error!("⚠️ Dashboard event stream lagged by {} event(s)", n);
+                        None
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                let status = crate::event_handler::status_response(&devices_d.snapshot());
+                match event {
+                    Some(event) => registry_d.publish(event).await,
+                    None => registry_d.send_to_dashboards(&status, |c| c.subscribed).await,
+                }
+                registry_d.push_status(&status).await;
+            }
+        });
+
+        // Media watcher: global playing state and dashboard MediaChanged events
+        let registry_m = self.registry.clone();
+        let devices_m = self.devices.clone();
+        let media = self.services.media.clone();
+        tokio::spawn(async move {
+            let mut last: Option<(String, String, String, String)> = None;
+            loop {
+                let metadata = media.current_metadata().await;
+                let playing = metadata.as_ref().is_some_and(|m| m.status == "Playing");
+                if devices_m.snapshot().media_playing != playing {
+                    devices_m.submit(move |state| state.media_playing = playing);
+                }
+
+                // Position moves on every poll; only a new player, track or status is news
+                let key = metadata.as_ref().map(|m| (m.player_name.clone(), m.track_id.clone(), m.title.clone(), m.status.clone()));
+                if key != last {
+                    last = key;
+                    registry_m.publish(DashboardEvent::MediaChanged { metadata }).await;
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            }
         });
 
         // UDP Discovery Responder
-        let fp_c = fingerprint.clone();
+        let identity_d = identity.clone();
+        let server_name = self.options.server_name.clone();
+        let discovery_addr = self.options.discovery_addr;
         tokio::spawn(async move {
-            let socket = match std::net::UdpSocket::bind(wc_core::constants::DISCOVERY_ADDR) {
+            let Some(discovery_addr) = discovery_addr else {
+                log::info!("📡 UDP Discovery Responder disabled");
+                return;
+            };
+            let socket = match std::net::UdpSocket::bind(discovery_addr) {
                 Ok(s) => {
-                    log::info!("✅ UDP Discovery socket bound successfully to {}", wc_core::constants::DISCOVERY_ADDR);
+                    log::info!("✅ UDP Discovery socket bound successfully to {}", discovery_addr);
                     s
                 },
                 Err(e) => {
-                    log::error!("❌ Failed to bind UDP Discovery socket on {}: {}", wc_core::constants::DISCOVERY_ADDR, e);
-                    log::error!("   Check if port {} is already in use or blocked by firewall", wc_core::constants::DISCOVERY_PORT);
+                    log::error!("❌ Failed to bind UDP Discovery socket on {}: {}", discovery_addr, e);
+                    log::error!("   Check if port {} is already in use or blocked by firewall", discovery_addr.port());
                     return;
                 }
             };
@@ -163,7 +401,7 @@ impl InputServer {
             }
             
             let mut buf = [0u8; 1024];
-            log::info!("📡 UDP Discovery Responder active on port {}", wc_core::constants::DISCOVERY_PORT);
+            log::info!("📡 UDP Discovery Responder active on port {}", discovery_addr.port());
             log::info!("   Waiting for discovery broadcasts from Android devices...");
             
             loop {
@@ -174,17 +412,13 @@ impl InputServer {
                         
                         if msg.contains("discovery") {
                             log::info!("🔍 Discovery request detected from {}", src);
-                            let server_name = get_server_host_name();
-                            let response = crate::protocol::ControlResponse::DiscoveryResponse {
+                            let response = wc_protocol::ControlResponse::DiscoveryResponse {
                                 server_name: server_name.clone(),
-                                fingerprint: Some(fp_c.clone()),
+                                fingerprint: Some(identity_d.fingerprint()),
                             };
                             
-                            match rmp_serde::encode::to_vec_named(&response) {
-                                Ok(bin) => {
-                                    let mut packet = (bin.len() as u32).to_be_bytes().to_vec();
-                                    packet.extend_from_slice(&bin);
-                                    
+                            match framing::encode(&response) {
+                                Ok(packet) => {
                                     match socket.send_to(&packet, src) {
                                         Ok(sent) => {
                                             log::info!("📤 Sent discovery response to {} ({} bytes): server_name='{}'", src, sent, server_name);
@@ -211,39 +445,44 @@ impl InputServer {
 
         loop {
             let (socket, addr) = listener.accept().await?;
+            let device_ip = peer_ip(&addr);
+            let Some(permit) = handler.limits.admit(&device_ip) else { continue };
             let handler = handler.clone();
             let acceptor = acceptor.clone();
-            let audio_analyzer = self.audio_analyzer.clone();
+            let services = self.services.clone();
             let screen_streamer = self.screen_streamer.clone();
-            let media_manager = self.media_manager.clone();
             let registry = self.registry.clone();
+            let devices = self.devices.clone();
             
             tokio::spawn(async move {
-                let socket = match acceptor.accept(socket).await {
-                    Ok(s) => s,
-                    Err(e) => {
+                let _permit = permit;
+                // TLS and the PairRequest both have to happen before this
+                let handshake_deadline = tokio::time::Instant::now() + crate::limits::HANDSHAKE_TIMEOUT;
+                let socket = match tokio::time::timeout_at(handshake_deadline, acceptor.accept(socket)).await {
+                    Ok(Ok(s)) => s,
+                    Ok(Err(e)) => {
                         error!("❌ TLS Handshake failed: {}", e);
                         return;
                     }
+                    Err(_) => {
+                        warn!("⏱️ TLS Handshake with {} timed out", addr);
+                        return;
+                    }
                 };
 
                 let _ = socket.get_ref().0.set_nodelay(true); // Disable Nagle before splitting - critical for real-time audio!
+                let cert_fingerprint = socket.get_ref().1.peer_certificates()
+                    .and_then(|certs| certs.first())
+                    .map(|cert| crate::tls_utils::certificate_fingerprint(&cert.0));
                 let (mut reader, mut writer) = tokio::io::split(socket);
                 let device_addr = addr.to_string(); // Use IP:Port for absolute uniqueness
-                
-                let mut device_ip = addr.ip().to_string();
-                if device_ip.starts_with("::ffff:") {
-                    device_ip = device_ip.replace("::ffff:", "");
-                }
-                if device_ip == "::1" {
-                    device_ip = "127.0.0.1".to_string();
-                }
+                let session = Arc::new(Session::new(device_addr.clone(), device_ip, cert_fingerprint, devices.clone()));
                 let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(64); // Increased buffer to handle audio + frame bursts
                 info!("🔌 New connection from: {}", device_addr);
-                registry.add(device_addr.clone(), tx.clone());
+                registry.add(session.clone(), tx.clone());
                 
                 // Writer Task
-                tokio::spawn(async move {
+                let writer_task = tokio::spawn(async move {
                     while let Some(msg) = rx.recv().await {
                         if writer.write_all(&msg).await.is_err() { break; }
                         let _ = writer.flush().await;
@@ -251,24 +490,15 @@ impl InputServer {
                 });
 
                 // Metadata Task
-                let audio_analyzer_m = audio_analyzer.clone();
-                let media_manager_m = media_manager.clone();
+                let services_m = services.clone();
                 let tx_m = tx.clone();
-                tokio::spawn(async move {
+                let metadata_task = tokio::spawn(async move {
                     loop {
-                        if let Some(metadata) = media_manager_m.get_current_player_metadata().await {
-                            audio_analyzer_m.set_target_app(Some(metadata.player_name.clone()));
-                            
-                            // Update global playing state
-                            {
-                                let mut state = STATE.lock().unwrap();
-                                state.media_playing = metadata.status == "Playing";
-                            }
+                        if let Some(metadata) = services_m.media.current_metadata().await {
+                            services_m.spectrum.set_target_app(Some(metadata.player_name.clone()));
 
-                            let pkt = crate::protocol::ControlResponse::MediaStatus { metadata: Some(metadata) };
-                            if let Ok(bin) = rmp_serde::encode::to_vec_named(&pkt) {
-                                let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-                                msg.extend_from_slice(&bin);
+                            let pkt = wc_protocol::ControlResponse::MediaStatus { metadata: Some(metadata) };
+                            if let Ok(msg) = framing::encode(&pkt) {
                                 if tx_m.send(msg).await.is_err() { break; }
                             }
                         }
@@ -278,26 +508,21 @@ impl InputServer {
 
                 // Spectrum Task (Fast & High Priority)
                 let tx_s = tx.clone();
-                let device_ip_s = device_ip.clone();
-                let audio_analyzer_s = audio_analyzer.clone();
-                tokio::spawn(async move {
+                let session_s = session.clone();
+                let devices_s = devices.clone();
+                let spectrum = services.spectrum.clone();
+                let spectrum_task = tokio::spawn(async move {
                     let mut was_playing = true;
                     loop {
                         tokio::time::sleep(tokio::time::Duration::from_millis(16)).await;
-                        let (is_trusted, media_playing) = {
-                            let state = STATE.lock().unwrap();
-                            let trusted = state.devices.values().any(|d| d.ip == device_ip_s && d.status == "Trusted");
-                            (trusted, state.media_playing)
-                        };
-                        if !is_trusted { continue; }
+                        if !session_s.supports(capability::SPECTRUM_7BAND) || !session_s.has_permission(Permission::Media) { continue; }
+                        let media_playing = devices_s.snapshot().media_playing;
 
                         if !media_playing {
                             if was_playing {
-                                let spectrum_pkt = crate::protocol::BinaryPacket::Spectrum { bands: vec![0.0; 7] };
-                                if let Ok(bin) = rmp_serde::encode::to_vec_named(&spectrum_pkt) {
-                                    let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-                                    msg.extend_from_slice(&bin);
-                                    let _ = tx_s.try_send(msg);
+                                let spectrum_pkt = wc_protocol::BinaryPacket::Spectrum { bands: vec![0.0; 7] };
+                                if let Ok(bin) = framing::encode_body(&spectrum_pkt) {
+                                    let _ = send_media(&session_s, &tx_s, bin);
                                 }
                                 was_playing = false;
                             }
@@ -305,21 +530,19 @@ impl InputServer {
                         }
                         
                         was_playing = true;
-                        let bands = audio_analyzer_s.get_levels();
-                        let spectrum_pkt = crate::protocol::BinaryPacket::Spectrum { bands };
-                        if let Ok(bin) = rmp_serde::encode::to_vec_named(&spectrum_pkt) {
-                            let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-                            msg.extend_from_slice(&bin);
-                            let _ = tx_s.try_send(msg); 
+                        let bands = spectrum.levels();
+                        let spectrum_pkt = wc_protocol::BinaryPacket::Spectrum { bands };
+                        if let Ok(bin) = framing::encode_body(&spectrum_pkt) {
+                            let _ = send_media(&session_s, &tx_s, bin);
                         }
                     }
                 });
 
                 // Screen Frame Task (Lower Priority, Heavy)
                 let tx_f = tx.clone();
-                let device_ip_f = device_ip.clone();
+                let session_f = session.clone();
                 let screen_streamer_f = screen_streamer.clone();
-                tokio::spawn(async move {
+                let frame_task = tokio::spawn(async move {
                     let mut last_frame_data: Option<Vec<u8>> = None;
                     let mut trust_check_counter = 0;
                     let mut is_trusted = false;
@@ -329,10 +552,7 @@ impl InputServer {
                         
                         // Check trust status every ~1 second (60 iterations) to save CPU
                         if trust_check_counter == 0 {
-                            is_trusted = {
-                                let state = STATE.lock().unwrap();
-                                state.devices.values().any(|d| d.ip == device_ip_f && d.status == "Trusted")
-                            };
+                            is_trusted = session_f.supports(capability::MIRRORING_JPEG) && session_f.has_permission(Permission::Mirror);
                             trust_check_counter = 60;
                         }
                         trust_check_counter -= 1;
@@ -351,25 +571,22 @@ impl InputServer {
                             };
                             
                             if is_new {
-                                let frame_pkt = crate::protocol::BinaryPacket::Frame { b: frame.clone() };
-                                if let Ok(bin) = rmp_serde::encode::to_vec_named(&frame_pkt) {
-                                    let mut msg = (bin.len() as u32).to_be_bytes().to_vec();
-                                    msg.extend_from_slice(&bin);
-                                    
+                                let frame_pkt = wc_protocol::BinaryPacket::Frame { b: frame.clone() };
+                                if let Ok(bin) = framing::encode_body(&frame_pkt) {
                                     // Use try_send to avoid blocking the loop if network is slow
                                     // Slow network should result in dropped frames, not lag.
-                                    match tx_f.try_send(msg) {
+                                    match send_media(&session_f, &tx_f, bin) {
                                         Ok(_) => {
                                             if last_frame_data.is_none() {
-                                                info!("🖼️ [SIGNAL] First frame successfully transmitted to client at {}! (Size: {} bytes)", device_ip_f, frame.len());
+                                                info!("🖼️ [SIGNAL] First frame successfully transmitted to client at {}! (Size: {} bytes)", session_f.addr, frame.len());
                                             }
                                             last_frame_data = Some(frame);
                                         }
                                         Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
-                                            debug!("⚠️ [SIGNAL] Frame dropped for {}: channel full", device_ip_f);
+                                            debug!("⚠️ [SIGNAL] Frame dropped for {}: channel full", session_f.addr);
                                         }
                                         Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
-                                            error!("❌ [SIGNAL] Transmission channel closed for {}", device_ip_f);
+                                            error!("❌ [SIGNAL] Transmission channel closed for {}", session_f.addr);
                                             break;
                                         }
                                     }
@@ -379,29 +596,118 @@ impl InputServer {
                     }
                 });
 
-                // Reader Loop
-                let mut len_buf = [0u8; 4];
-                while reader.read_exact(&mut len_buf).await.is_ok() {
-                    let len = u32::from_be_bytes(len_buf) as usize;
-                    if len > 10 * 1024 * 1024 { break; }
-                    let mut payload = vec![0u8; len];
-                    if reader.read_exact(&mut payload).await.is_ok() {
-                        if let Ok(event) = rmp_serde::from_slice::<InputEvent>(&payload) {
-                             if handler.handle_event(event, &device_ip, &device_addr, &tx).await { break; }
+                // Reader Loop. Until the device is trusted it only gets small frames.
+                loop {
+                    let limit = if session.is_trusted() { framing::MAX_FRAME_LEN } else { framing::MAX_PREAUTH_FRAME_LEN };
+                    let read = framing::read_frame_limited(&mut reader, limit);
+                    let payload = if session.device_id().is_some() {
+                        read.await
+                    } else {
+                        match tokio::time::timeout_at(handshake_deadline, read).await {
+                            Ok(payload) => payload,
+                            Err(_) => {
+                                warn!("⏱️ {} never sent a PairRequest, closing", device_addr);
+                                break;
+                            }
                         }
-                    } else { break; }
+                    };
+                    let Ok(payload) = payload else { break };
+                    if let Ok(message) = framing::decode::<ClientMessage>(&payload) {
+                         if handler.handle_event(message, &session, &tx).await { break; }
+                    }
                 }
 
-                let mut state = STATE.lock().unwrap();
-                state.devices.retain(|_, d| !(d.ip == device_ip && d.status == "Pending"));
-                state.save();
+                if let Some(id) = session.device_id() {
+                    registry.publish(DashboardEvent::DeviceDisconnected { id: id.clone() }).await;
+                    let addr = device_addr.clone();
+                    devices.submit(move |state| {
+                        if state.devices.get(&id).is_some_and(|d| d.status == "Pending") {
+                            state.devices.remove(&id);
+                        }
+                        if state.pairing_connections.get(&id) == Some(&addr) {
+                            state.pairing_connections.remove(&id);
+                        }
+                    });
+                }
+                handler.release_held_input(&session).await;
+                if let Some(media) = session.media() {
+                    media.close();
+                }
                 registry.remove(&device_addr);
                 screen_streamer.stop();
+
+                // The producers hold senders forever; once they're gone the
+                // writer drains what's queued and ends too, unless the peer
+                // stopped reading.
+                metadata_task.abort();
+                spectrum_task.abort();
+                frame_task.abort();
+                drop(tx);
+                let writer_abort = writer_task.abort_handle();
+                if tokio::time::timeout(WRITER_DRAIN_TIMEOUT, writer_task).await.is_err() {
+                    writer_abort.abort();
+                }
             });
         }
     }
 }
 
+/// How long a closing connection may take to flush its queued messages.
+const WRITER_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The peer's address as sessions record it: IPv4-mapped addresses unwrapped
+/// and IPv6 loopback folded into `127.0.0.1`.
+fn peer_ip(addr: &SocketAddr) -> String {
+    let mut ip = addr.ip().to_string();
+    if ip.starts_with("::ffff:") {
+        ip = ip.replace("::ffff:", "");
+    }
+    if ip == "::1" {
+        ip = "127.0.0.1".to_string();
+    }
+    ip
+}
+
+/// Channel id of `BinaryPacket`s on the QUIC media transport.
+const MEDIA_CHANNEL: u8 = 1;
+
+/// Sends an encoded `BinaryPacket` as QUIC datagrams when the session has a
+/// media channel attached, otherwise on its TLS stream.
+fn send_media(session: &Session, tx: &Sender<Vec<u8>>, bin: Vec<u8>) -> Result<(), tokio::sync::mpsc::error::TrySendError<Vec<u8>>> {
+    if let Some(media) = session.media() {
+        match media.send(MEDIA_CHANNEL, &bin) {
+            Ok(()) => return Ok(()),
+            Err(e) => debug!("QUIC media send failed for {}, using TLS: {}", session.addr, e),
+        }
+    }
+    tx.try_send(framing::frame(&bin))
+}
+
+/// Ties a QUIC peer to the TLS session whose media token it presents, then
+/// routes that session's spectrum and frames through its datagrams until
+/// either side goes away. The peer counts against its address's connection
+/// cap until then.
+async fn attach_media_peer(mut peer: QuicPeer, _permit: crate::limits::ConnectionPermit, registry: Arc<ConnectionRegistry>) {
+    let token = match tokio::time::timeout(crate::limits::HANDSHAKE_TIMEOUT, peer.receive()).await {
+        Ok(Ok((wc_transport::server::CONTROL_CHANNEL, token))) => String::from_utf8_lossy(&token).to_string(),
+        _ => {
+            peer.close();
+            return;
+        }
+    };
+    let Some(session) = registry.session_by_media_token(&token) else {
+        error!("⚠️ QUIC peer {} presented an unknown media token", peer.remote_address());
+        peer.close();
+        return;
+    };
+
+    info!("🎞️ Media for {} now goes over QUIC ({})", session.addr, peer.remote_address());
+    session.attach_media(peer.media_sender());
+    while peer.receive().await.is_ok() {}
+    session.detach_media();
+    info!("🎞️ QUIC media for {} closed, back to TLS", session.addr);
+}
+
 fn get_server_host_name() -> String {
     std::process::Command::new("hostname").output()
         .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
@@ -0,0 +1,158 @@
+//! The desktop the server talks to besides the input device: media players,
+//! audio levels, monitor layout, notifications and installed applications.
+//!
+//! Each one sits behind a trait so the server also runs where there is no
+//! session bus, no audio and no compositor; `crate::fakes` (the `fakes` feature) has in-memory
+//! versions for that.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use notify_rust::Notification;
+use tokio::sync::oneshot;
+use wc_protocol::{AppInfo, MediaMetadata, MonitorInfo};
+
+#[async_trait]
+pub trait MediaSource: Send + Sync {
+    /// The player the phone should see, if any.
+    async fn current_metadata(&self) -> Option<MediaMetadata>;
+    /// "play", "pause", "play_pause", "next", "previous"
+    async fn send_command(&self, command: &str) -> anyhow::Result<()>;
+}
+
+/// Band levels for the phone's spectrum view.
+pub trait SpectrumSource: Send + Sync {
+    fn levels(&self) -> Vec<f32>;
+    fn set_sensitivity(&self, value: f32);
+    /// Narrows capture to the player currently shown, when possible.
+    fn set_target_app(&self, app: Option<String>);
+}
+
+pub trait MonitorProvider: Send + Sync {
+    fn monitors(&self) -> Vec<MonitorInfo>;
+}
+
+/// Applications a device may list and launch.
+pub trait AppCatalog: Send + Sync {
+    fn installed_apps(&self) -> Vec<AppInfo>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    pub summary: String,
+    pub body: String,
+    pub icon: String,
+}
+
+impl Notice {
+    pub fn new(summary: &str, body: impl Into<String>, icon: &str) -> Self {
+        Self { summary: summary.to_string(), body: body.into(), icon: icon.to_string() }
+    }
+}
+
+pub trait Notifier: Send + Sync {
+    fn notify(&self, notice: Notice);
+    /// Shows `notice` with Approve/Decline actions. The receiver yields the
+    /// choice; it is dropped unanswered when the notice is dismissed.
+    fn ask(&self, notice: Notice) -> oneshot::Receiver<bool>;
+}
+
+/// Everything `InputServer` needs from the desktop.
+#[derive(Clone)]
+pub struct Services {
+    pub media: Arc<dyn MediaSource>,
+    pub spectrum: Arc<dyn SpectrumSource>,
+    pub monitors: Arc<dyn MonitorProvider>,
+    pub notifier: Arc<dyn Notifier>,
+    pub apps: Arc<dyn AppCatalog>,
+}
+
+impl Services {
+    /// MPRIS over the session bus, audio capture, hyprctl and desktop
+    /// notifications.
+    pub async fn desktop() -> anyhow::Result<Self> {
+        let audio_analyzer = crate::audio_analyzer::AudioAnalyzer::new();
+        audio_analyzer.start();
+
+        Ok(Self {
+            media: Arc::new(crate::media_manager::MediaManager::new().await?),
+            spectrum: Arc::new(audio_analyzer),
+            monitors: Arc::new(HyprctlMonitors),
+            notifier: Arc::new(DesktopNotifier),
+            apps: Arc::new(crate::app_manager::AppManager {}),
+        })
+    }
+}
+
+/// Monitor layout from `hyprctl monitors -j`; empty when Hyprland isn't running.
+pub struct HyprctlMonitors;
+
+impl MonitorProvider for HyprctlMonitors {
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        let output = std::process::Command::new("hyprctl")
+            .arg("monitors")
+            .arg("-j")
+            .output();
+
+        if let Ok(out) = output {
+            if let Ok(val) = serde_json::from_slice::<serde_json::Value>(&out.stdout) {
+                if let Some(arr) = val.as_array() {
+                    return arr.iter().map(|m| {
+                        MonitorInfo {
+                            id: m.get("id").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+                            name: m.get("name").and_then(|v| v.as_str()).unwrap_or("Unknown").to_string(),
+                            width: m.get("width").and_then(|v| v.as_i64()).unwrap_or(1920) as i32,
+                            height: m.get("height").and_then(|v| v.as_i64()).unwrap_or(1080) as i32,
+                            x: m.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+                            y: m.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+                            focused: m.get("focused").and_then(|v| v.as_bool()).unwrap_or(false),
+                            scale: m.get("scale").and_then(|v| v.as_f64()).unwrap_or(1.0),
+                            transform: m.get("transform").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+                        }
+                    }).collect();
+                }
+            }
+        }
+        vec![]
+    }
+}
+
+/// Freedesktop notifications through `notify_rust`.
+pub struct DesktopNotifier;
+
+impl DesktopNotifier {
+    fn notification(notice: &Notice) -> Notification {
+        let mut notification = Notification::new();
+        notification
+            .appname("Wayland Connect")
+            .summary(&notice.summary)
+            .body(&notice.body)
+            .icon(&notice.icon);
+        notification
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, notice: Notice) {
+        let _ = Self::notification(&notice).show();
+    }
+
+    fn ask(&self, notice: Notice) -> oneshot::Receiver<bool> {
+        let (tx, rx) = oneshot::channel();
+        // Waiting for the action blocks, so it gets its own thread
+        std::thread::spawn(move || {
+            let notification = Self::notification(&notice)
+                .action("approve", "Approve")
+                .action("decline", "Decline")
+                .show();
+
+            if let Ok(handle) = notification {
+                handle.wait_for_action(move |action| match action {
+                    "approve" => { let _ = tx.send(true); }
+                    "decline" => { let _ = tx.send(false); }
+                    _ => {}
+                });
+            }
+        });
+        rx
+    }
+}
@@ -1,75 +1,155 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 use std::sync::Mutex;
-use lazy_static::lazy_static;
-use crate::protocol::DeviceInfo;
-
-#[derive(Debug, Clone)]
-pub struct PendingMirror {
-    pub _device_id: String,
-    pub width: u32,
-    pub height: u32,
-    pub fps: u32,
-    pub monitor: i32,
+use std::sync::atomic::{AtomicBool, Ordering};
+use rand::Rng;
+use wc_transport::server::MediaSender;
+use wc_protocol::{capability, Permission};
+use crate::device_registry::DeviceRegistry;
+
+pub fn get_config_dir() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push(wc_core::constants::CONFIG_DIR_NAME);
+    if !path.exists() {
+        let _ = std::fs::create_dir_all(&path);
+    }
+    path
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct AppState {
-    pub devices: HashMap<String, DeviceInfo>,
-    #[serde(default)]
-    pub media_playing: bool,
-    #[serde(default)]
-    pub zoom_enabled: bool,
-    #[serde(default = "default_true")]
-    pub auto_connect: bool,
-    #[serde(skip)]
-    pub pending_mirror: Option<PendingMirror>,
-    #[serde(skip)]
-    pub mirroring_device: Option<String>,
+/// State owned by a single client connection.
+///
+/// Trust is never derived from the peer address: a session starts out
+/// anonymous and is bound to exactly one device id by its `PairRequest`.
+/// Every privileged action is then authorized against that device.
+pub struct Session {
+    pub addr: String,
+    pub ip: String,
+    /// Fingerprint of the client certificate presented in the TLS handshake.
+    pub cert_fingerprint: Option<String>,
+    /// Came in over the local control socket from the same user.
+    pub local: bool,
+    device_id: Mutex<Option<String>>,
+    registry: DeviceRegistry,
+    capabilities: Mutex<BTreeSet<String>>,
+    /// Presented by the client on the QUIC control stream to attach media.
+    pub media_token: String,
+    media: Mutex<Option<MediaSender>>,
+    /// Keys pressed with `KeyDown` and not released yet
+    held_keys: Mutex<BTreeSet<String>>,
+    /// The last `TouchFrame` left fingers down
+    touching: AtomicBool,
+    /// The last `ScreenTouch` left the finger down
+    screen_touching: AtomicBool,
 }
 
-fn default_true() -> bool { true }
-
-impl AppState {
-    pub fn load() -> Self {
-        let config_dir = get_config_dir();
-        let file_path = config_dir.join("devices.json");
-        
-        if let Ok(mut file) = File::open(file_path) {
-            let mut content = String::new();
-            if file.read_to_string(&mut content).is_ok() {
-                if let Ok(mut state) = serde_json::from_str::<AppState>(&content) {
-                    state.mirroring_device = None;
-                    return state;
-                }
-            }
+impl Session {
+    pub fn new(addr: String, ip: String, cert_fingerprint: Option<String>, registry: DeviceRegistry) -> Self {
+        let capabilities = capability::LEGACY.iter().map(|c| c.to_string()).collect();
+        let media_token = rand::thread_rng().gen::<[u8; 16]>().iter().map(|b| format!("{:02x}", b)).collect();
+        Self {
+            addr,
+            ip,
+            cert_fingerprint,
+            local: false,
+            device_id: Mutex::new(None),
+            registry,
+            capabilities: Mutex::new(capabilities),
+            media_token,
+            media: Mutex::new(None),
+            held_keys: Mutex::new(BTreeSet::new()),
+            touching: AtomicBool::new(false),
+            screen_touching: AtomicBool::new(false),
         }
-        AppState { devices: HashMap::new(), media_playing: false, zoom_enabled: false, auto_connect: true, pending_mirror: None, mirroring_device: None }
     }
 
-    pub fn save(&self) {
-        let config_dir = get_config_dir();
-        let file_path = config_dir.join("devices.json");
-        
-        if let Ok(json) = serde_json::to_string_pretty(self) {
-            if let Ok(mut file) = File::create(file_path) {
-                let _ = file.write_all(json.as_bytes());
+    pub fn local(addr: String, registry: DeviceRegistry) -> Self {
+        Self { local: true, ..Self::new(addr, "localhost".to_string(), None, registry) }
+    }
+
+    pub fn attach_media(&self, sender: MediaSender) {
+        *self.media.lock().unwrap() = Some(sender);
+    }
+
+    pub fn detach_media(&self) {
+        *self.media.lock().unwrap() = None;
+    }
+
+    /// The QUIC datagram sender, while its connection is still up.
+    pub fn media(&self) -> Option<MediaSender> {
+        self.media.lock().unwrap().clone().filter(|m| !m.is_closed())
+    }
+
+    /// Replaces the legacy defaults with what both sides support and returns
+    /// the negotiated set.
+    pub fn negotiate_capabilities(&self, peer: &[String]) -> BTreeSet<String> {
+        let negotiated: BTreeSet<String> = peer.iter()
+            .filter(|c| capability::SERVER.contains(&c.as_str()))
+            .cloned()
+            .collect();
+        *self.capabilities.lock().unwrap() = negotiated.clone();
+        negotiated
+    }
+
+    pub fn hold_key(&self, key: &str) {
+        self.held_keys.lock().unwrap().insert(key.to_string());
+    }
+
+    pub fn release_key(&self, key: &str) {
+        self.held_keys.lock().unwrap().remove(key);
+    }
+
+    /// Keys still down, e.g. when the connection drops mid-press.
+    pub fn take_held_keys(&self) -> BTreeSet<String> {
+        std::mem::take(&mut *self.held_keys.lock().unwrap())
+    }
+
+    pub fn set_touching(&self, touching: bool) {
+        self.touching.store(touching, Ordering::Relaxed);
+    }
+
+    /// Whether fingers were left down, clearing the flag.
+    pub fn take_touching(&self) -> bool {
+        self.touching.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn set_screen_touching(&self, touching: bool) {
+        self.screen_touching.store(touching, Ordering::Relaxed);
+    }
+
+    /// Whether the mirrored screen was left touched, clearing the flag.
+    pub fn take_screen_touching(&self) -> bool {
+        self.screen_touching.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.lock().unwrap().contains(capability)
+    }
+
+    pub fn device_id(&self) -> Option<String> {
+        self.device_id.lock().unwrap().clone()
+    }
+
+    /// Binds the session to `id`. A session can only ever speak for one
+    /// device, so rebinding to a different id is refused.
+    pub fn bind_device(&self, id: &str) -> bool {
+        let mut device_id = self.device_id.lock().unwrap();
+        match device_id.as_deref() {
+            Some(existing) => existing == id,
+            None => {
+                *device_id = Some(id.to_string());
+                true
             }
         }
     }
-}
 
-pub fn get_config_dir() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push(wc_core::constants::CONFIG_DIR_NAME);
-    if !path.exists() {
-        let _ = std::fs::create_dir_all(&path);
+    pub fn is_trusted(&self) -> bool {
+        let Some(id) = self.device_id() else { return false };
+        self.registry.snapshot().is_trusted(&id)
     }
-    path
-}
 
-lazy_static! {
-    pub static ref STATE: Mutex<AppState> = Mutex::new(AppState::load());
+    /// Whether the bound device is trusted *and* holds `permission`.
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        let Some(id) = self.device_id() else { return false };
+        self.registry.snapshot().has_permission(&id, permission)
+    }
 }
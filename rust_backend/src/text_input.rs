@@ -0,0 +1,354 @@
+//! `TypeText` support: text the desktop's layout has keys for is typed on
+//! the uinput keyboard; anything else goes through the compositor, with a
+//! `zwp_virtual_keyboard_v1` carrying a keymap made for it or, failing
+//! that, `zwp_input_method_v2.commit_string`.
+
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+use tokio::sync::oneshot;
+use log::{debug, info, warn};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_keyboard, wl_registry, wl_seat};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle, WEnum};
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_manager_v2::ZwpInputMethodManagerV2;
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_v2::{self, ZwpInputMethodV2};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+use crate::xkb::{keysym_for, Keymap, Stroke};
+
+/// Name of the sections in keymaps we upload, so they aren't mistaken for
+/// the desktop's layout when the compositor echoes them back.
+const KEYMAP_NAME: &str = "wayland-connect";
+/// Characters per uploaded keymap; X11 clients can't see keycodes past 255.
+const MAX_KEYMAP_CHARS: usize = 200;
+
+/// How a `TypeText` is entered. Keys and text reach the compositor by
+/// different paths with no ordering between them, so a text goes entirely
+/// one way.
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    /// Typed on the uinput keyboard
+    Keys(Vec<Stroke>),
+    /// Needs the compositor
+    Text(String),
+}
+
+/// Runs `TextInput` on its own thread, as its Wayland roundtrips block.
+pub struct TextTyper {
+    jobs: mpsc::Sender<(String, oneshot::Sender<anyhow::Result<Vec<Stroke>>>)>,
+}
+
+impl TextTyper {
+    pub fn spawn() -> anyhow::Result<Self> {
+        let (jobs, rx) = mpsc::channel::<(String, oneshot::Sender<_>)>();
+        thread::Builder::new().name("text-input".to_string()).spawn(move || {
+            let mut text_input = TextInput::default();
+            for (text, reply) in rx {
+                let result = match text_input.plan(&text) {
+                    Segment::Keys(strokes) => Ok(strokes),
+                    Segment::Text(text) => text_input.type_fallback(&text).map(|()| Vec::new()),
+                };
+                let _ = reply.send(result);
+            }
+        })?;
+        Ok(Self { jobs })
+    }
+
+    /// The keys that type `text` on the uinput keyboard. Text that needs the
+    /// compositor is entered by this and comes back as no keys.
+    pub async fn prepare(&self, text: String) -> anyhow::Result<Vec<Stroke>> {
+        let (reply, result) = oneshot::channel();
+        self.jobs.send((text, reply)).map_err(|_| anyhow::anyhow!("Text input thread is gone"))?;
+        result.await?
+    }
+}
+
+/// Layout lookup and compositor fallback, connected on first use.
+#[derive(Default)]
+pub struct TextInput {
+    wayland: Option<WaylandText>,
+    /// The compiled layout and the keymap text it came from, if any
+    keymap: Option<(Option<String>, Keymap)>,
+}
+
+impl TextInput {
+    /// Keys for `text` when the uinput keyboard can type all of it on the
+    /// layout, otherwise the whole text for the compositor.
+    ///
+    /// Characters are looked up in the keymap's first layout group: the
+    /// compositor only tells the focused client which group is active, and
+    /// this client never has focus. With several layouts configured, text
+    /// typed while another one is active can come out wrong.
+    pub fn plan(&mut self, text: &str) -> Segment {
+        self.refresh();
+        let strokes = text.chars()
+            .map(|c| self.keymap.as_mut().and_then(|(_, keymap)| keymap.resolve(c, 0)))
+            .collect::<Option<Vec<_>>>();
+        match strokes {
+            Some(strokes) => Segment::Keys(strokes),
+            None => Segment::Text(text.to_string()),
+        }
+    }
+
+    /// Enters `text` through the compositor.
+    pub fn type_fallback(&mut self, text: &str) -> anyhow::Result<()> {
+        let Some(wayland) = self.wayland.as_mut() else {
+            anyhow::bail!("No Wayland connection to type {:?} with", text);
+        };
+        let result = wayland.type_text(text);
+        if result.is_err() {
+            // Reconnect next time in case the compositor went away
+            self.wayland = None;
+        }
+        result
+    }
+
+    /// Picks up layout changes, compiling the compositor's keymap when it
+    /// sent a new one and the environment's when there is no compositor.
+    fn refresh(&mut self) {
+        if self.wayland.is_none() {
+            match WaylandText::connect() {
+                Ok(wayland) => self.wayland = Some(wayland),
+                Err(e) => debug!("⌨️ No Wayland text input: {}", e),
+            }
+        }
+        if let Some(wayland) = self.wayland.as_mut() {
+            if let Err(e) = wayland.queue.roundtrip(&mut wayland.state) {
+                warn!("⚠️ Wayland connection lost: {}", e);
+                self.wayland = None;
+            }
+        }
+
+        let current = self.wayland.as_ref().and_then(|w| w.state.keymap.clone());
+        match (&current, &self.keymap) {
+            (Some(text), Some((Some(compiled), _))) if text == compiled => {}
+            (None, Some(_)) => {}
+            (Some(text), _) => {
+                info!("⌨️ Using the compositor's keymap for typed text");
+                self.keymap = Keymap::from_string(text).map(|keymap| (current, keymap));
+            }
+            (None, None) => {
+                self.keymap = Keymap::from_environment().map(|keymap| (None, keymap));
+            }
+        }
+    }
+}
+
+struct WaylandText {
+    queue: EventQueue<State>,
+    state: State,
+    started: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    seat: Option<wl_seat::WlSeat>,
+    virtual_keyboards: Option<ZwpVirtualKeyboardManagerV1>,
+    input_methods: Option<ZwpInputMethodManagerV2>,
+    /// Last keymap the compositor sent that isn't one of ours
+    keymap: Option<String>,
+    input_method: InputMethodState,
+}
+
+#[derive(Default)]
+struct InputMethodState {
+    pending_active: bool,
+    active: bool,
+    unavailable: bool,
+    /// `done` events so far, the serial `commit` refers to
+    done: u32,
+}
+
+impl WaylandText {
+    fn connect() -> anyhow::Result<Self> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+        let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=7, ())?;
+        seat.get_keyboard(&qh, ());
+        let mut state = State {
+            seat: Some(seat),
+            virtual_keyboards: globals.bind(&qh, 1..=1, ()).ok(),
+            input_methods: globals.bind(&qh, 1..=1, ()).ok(),
+            ..State::default()
+        };
+        queue.roundtrip(&mut state)?;
+        info!(
+            "⌨️ Wayland text input ready (virtual keyboard: {}, input method: {})",
+            state.virtual_keyboards.is_some(),
+            state.input_methods.is_some()
+        );
+        Ok(Self { queue, state, started: Instant::now() })
+    }
+
+    fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+        let seat = self.state.seat.clone().ok_or_else(|| anyhow::anyhow!("No seat"))?;
+        if let Some(manager) = self.state.virtual_keyboards.clone() {
+            return self.type_with_virtual_keyboard(&manager, &seat, text);
+        }
+        if let Some(manager) = self.state.input_methods.clone() {
+            return self.commit_with_input_method(&manager, &seat, text);
+        }
+        anyhow::bail!("The compositor offers neither a virtual keyboard nor an input method")
+    }
+
+    /// Uploads a keymap with one key per distinct character and presses them.
+    fn type_with_virtual_keyboard(&mut self, manager: &ZwpVirtualKeyboardManagerV1, seat: &wl_seat::WlSeat, text: &str) -> anyhow::Result<()> {
+        let keyboard = manager.create_virtual_keyboard(seat, &self.queue.handle(), ());
+        let result = self.press_on(&keyboard, text);
+        keyboard.destroy();
+        self.queue.roundtrip(&mut self.state)?;
+        result
+    }
+
+    fn press_on(&mut self, keyboard: &ZwpVirtualKeyboardV1, text: &str) -> anyhow::Result<()> {
+        let chars: Vec<char> = text.chars().collect();
+        for chunk in keymap_chunks(&chars) {
+            let keymap = text_keymap(&chunk.keys);
+            let fd = keymap_fd(&keymap)?;
+            keyboard.keymap(wl_keyboard::KeymapFormat::XkbV1.into(), fd.as_fd(), keymap.len() as u32);
+            self.queue.roundtrip(&mut self.state)?;
+            for c in chunk.text {
+                // Evdev codes, 8 below the XKB keycodes in the keymap
+                let key = chunk.keys.iter().position(|k| *k == c).unwrap_or(0) as u32 + 1;
+                let time = self.started.elapsed().as_millis() as u32;
+                keyboard.key(time, key, wl_keyboard::KeyState::Pressed.into());
+                keyboard.key(time, key, wl_keyboard::KeyState::Released.into());
+            }
+            self.queue.roundtrip(&mut self.state)?;
+        }
+        Ok(())
+    }
+
+    /// Commits `text` to the focused text field as an input method. Only
+    /// works while no other input method (e.g. fcitx) holds the seat.
+    fn commit_with_input_method(&mut self, manager: &ZwpInputMethodManagerV2, seat: &wl_seat::WlSeat, text: &str) -> anyhow::Result<()> {
+        self.state.input_method = InputMethodState::default();
+        let input_method = manager.get_input_method(seat, &self.queue.handle(), ());
+        self.queue.roundtrip(&mut self.state)?;
+
+        let im = &self.state.input_method;
+        let result = if im.unavailable {
+            Err(anyhow::anyhow!("Another input method is active"))
+        } else if !im.active {
+            Err(anyhow::anyhow!("No text field has focus"))
+        } else {
+            input_method.commit_string(text.to_string());
+            input_method.commit(im.done);
+            Ok(())
+        };
+        input_method.destroy();
+        self.queue.roundtrip(&mut self.state)?;
+        result
+    }
+}
+
+/// Characters typed with one keymap and the distinct ones it needs keys for.
+struct KeymapChunk {
+    keys: Vec<char>,
+    text: Vec<char>,
+}
+
+fn keymap_chunks(chars: &[char]) -> Vec<KeymapChunk> {
+    let mut chunks: Vec<KeymapChunk> = Vec::new();
+    for &c in chars {
+        match chunks.last_mut() {
+            Some(chunk) if chunk.keys.contains(&c) => chunk.text.push(c),
+            Some(chunk) if chunk.keys.len() < MAX_KEYMAP_CHARS => {
+                chunk.keys.push(c);
+                chunk.text.push(c);
+            }
+            _ => chunks.push(KeymapChunk { keys: vec![c], text: vec![c] }),
+        }
+    }
+    chunks
+}
+
+/// XKB keymap giving each of `keys` its own keycode, starting at 9.
+fn text_keymap(keys: &[char]) -> String {
+    let mut keycodes = String::new();
+    let mut symbols = String::new();
+    for (i, c) in keys.iter().enumerate() {
+        keycodes.push_str(&format!("    <K{}> = {};\n", i, i + 9));
+        symbols.push_str(&format!("    key <K{}> {{ [ 0x{:08x} ] }};\n", i, keysym_for(*c).raw()));
+    }
+    format!(
+        "xkb_keymap {{\n\
+         xkb_keycodes \"{name}\" {{\n    minimum = 8;\n    maximum = {max};\n{keycodes}}};\n\
+         xkb_types \"{name}\" {{ include \"complete\" }};\n\
+         xkb_compatibility \"{name}\" {{ include \"complete\" }};\n\
+         xkb_symbols \"{name}\" {{\n{symbols}}};\n\
+         }};\n\0",
+        name = KEYMAP_NAME,
+        max = keys.len() + 9,
+    )
+}
+
+/// A memfd holding `keymap`, which is how the virtual keyboard takes it.
+fn keymap_fd(keymap: &str) -> anyhow::Result<OwnedFd> {
+    let raw = unsafe { libc::memfd_create(c"wayland-connect-keymap".as_ptr(), libc::MFD_CLOEXEC) };
+    if raw < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let mut file = unsafe { File::from_raw_fd(raw) };
+    file.write_all(keymap.as_bytes())?;
+    Ok(file.into())
+}
+
+/// The keymap the compositor shares in `fd`. The fd's file offset is shared
+/// too, so it is mapped privately from the start as wl_keyboard asks rather
+/// than read.
+fn read_keymap(fd: &OwnedFd, size: usize) -> std::io::Result<String> {
+    if size == 0 {
+        return Ok(String::new());
+    }
+    let map = unsafe { libc::mmap(std::ptr::null_mut(), size, libc::PROT_READ, libc::MAP_PRIVATE, fd.as_raw_fd(), 0) };
+    if map == libc::MAP_FAILED {
+        return Err(std::io::Error::last_os_error());
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(map as *const u8, size) };
+    let text = String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string();
+    unsafe { libc::munmap(map, size) };
+    Ok(text)
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(_: &mut Self, _: &wl_registry::WlRegistry, _: wl_registry::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for State {
+    fn event(state: &mut Self, _: &wl_keyboard::WlKeyboard, event: wl_keyboard::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+        if let wl_keyboard::Event::Keymap { format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1), fd, size } = event {
+            match read_keymap(&fd, size as usize) {
+                Ok(text) if !text.contains(&format!("\"{}\"", KEYMAP_NAME)) => state.keymap = Some(text),
+                Ok(_) => {}
+                Err(e) => warn!("⚠️ Failed to read the compositor's keymap: {}", e),
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwpInputMethodV2, ()> for State {
+    fn event(state: &mut Self, _: &ZwpInputMethodV2, event: zwp_input_method_v2::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+        let im = &mut state.input_method;
+        match event {
+            zwp_input_method_v2::Event::Activate => im.pending_active = true,
+            zwp_input_method_v2::Event::Deactivate => im.pending_active = false,
+            zwp_input_method_v2::Event::Done => {
+                im.active = im.pending_active;
+                im.done += 1;
+            }
+            zwp_input_method_v2::Event::Unavailable => im.unavailable = true,
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore wl_seat::WlSeat);
+delegate_noop!(State: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(State: ZwpVirtualKeyboardV1);
+delegate_noop!(State: ZwpInputMethodManagerV2);
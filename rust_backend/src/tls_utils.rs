@@ -1,97 +1,362 @@
-use rcgen::{Certificate, CertificateParams, DistinguishedName, DnType, IsCa, SanType};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, SanType};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use tokio_rustls::rustls::{Certificate as RustlsCert, PrivateKey as RustlsKey, ServerConfig};
-use std::sync::Arc;
-use log::info;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_rustls::rustls::{Certificate as RustlsCert, PrivateKey as RustlsKey, RootCertStore, ServerConfig, SignatureScheme};
+use tokio_rustls::rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::{any_supported_type, CertifiedKey};
+use std::sync::{Arc, RwLock};
+use log::{error, info};
 use sha2::{Sha256, Digest};
+use wc_protocol::ControlResponse;
 
-pub fn load_tls_config() -> anyhow::Result<(Arc<ServerConfig>, String)> {
-    let config_dir = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(wc_core::constants::CONFIG_DIR_NAME);
-    
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir)?;
-    }
-
-    let cert_path = config_dir.join("cert.pem");
-    let key_path = config_dir.join("key.pem");
-
-    let (cert_chain, key) = if cert_path.exists() && key_path.exists() {
-        info!("📜 Loading existing TLS certificates from {:?}", config_dir);
-        let cert_data = fs::read(&cert_path)?;
-        let key_data = fs::read(&key_path)?;
-        
-        let mut cert_reader = std::io::BufReader::new(&cert_data[..]);
-        let certs = rustls_pemfile::certs(&mut cert_reader)?
-            .into_iter()
-            .map(RustlsCert)
-            .collect::<Vec<_>>();
-            
-        let mut key_reader = std::io::BufReader::new(&key_data[..]);
-        let mut keys = rustls_pemfile::pkcs8_private_keys(&mut key_reader)?;
-        if keys.is_empty() {
-            // Try RSA keys if PKCS8 is empty
-            key_reader = std::io::BufReader::new(&key_data[..]);
-            keys = rustls_pemfile::rsa_private_keys(&mut key_reader)?;
-        }
-        
-        if keys.is_empty() {
-             anyhow::bail!("No private key found in key.pem");
+/// Small certificate authority used to issue per-device client certificates.
+///
+/// The CA certificate is the only trust anchor of the client verifier, so a
+/// device can only present a certificate the daemon signed. Whether that
+/// certificate is still valid is decided by the fingerprint stored on the
+/// device's `DeviceInfo`, which is how revocation works.
+pub struct DeviceCa {
+    cert: Certificate,
+    cert_der: Vec<u8>,
+}
+
+/// A freshly signed client certificate, handed to the device once.
+pub struct IssuedCertificate {
+    pub cert_pem: String,
+    pub key_pem: String,
+    pub fingerprint: String,
+}
+
+impl DeviceCa {
+    pub fn load_or_create(config_dir: &Path) -> anyhow::Result<Self> {
+        let cert_path = config_dir.join("ca.pem");
+        let key_path = config_dir.join("ca-key.pem");
+
+        if cert_path.exists() && key_path.exists() {
+            info!("📜 Loading device CA from {:?}", config_dir);
+            let key_pair = KeyPair::from_pem(&fs::read_to_string(&key_path)?)?;
+            let cert_data = fs::read(&cert_path)?;
+            let mut cert_reader = std::io::BufReader::new(&cert_data[..]);
+            let cert_der = rustls_pemfile::certs(&mut cert_reader)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("No certificate found in ca.pem"))?;
+
+            // Signing only needs the issuer name and key, so rebuilding the CA
+            // from the same parameters yields the same issuer as the stored anchor.
+            let cert = Certificate::from_params(Self::params(Some(key_pair)))?;
+            Ok(Self { cert, cert_der })
+        } else {
+            info!("✨ Generating device CA...");
+            let cert = Certificate::from_params(Self::params(None))?;
+            let cert_der = cert.serialize_der()?;
+            fs::write(&cert_path, cert.serialize_pem()?)?;
+            write_private(&key_path, &cert.serialize_private_key_pem())?;
+            Ok(Self { cert, cert_der })
         }
-        
-        (certs, RustlsKey(keys[0].clone()))
-    } else {
-        info!("✨ Generating new self-signed TLS certificates...");
+    }
+
+    fn params(key_pair: Option<KeyPair>) -> CertificateParams {
         let mut params = CertificateParams::default();
         params.not_before = rcgen::date_time_ymd(2023, 1, 1);
-        params.not_after = rcgen::date_time_ymd(2033, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2043, 1, 1);
         params.distinguished_name = DistinguishedName::new();
-        params.distinguished_name.push(DnType::CommonName, "WaylandConnect Backend");
-        params.subject_alt_names = vec![SanType::DnsName("localhost".to_string())];
+        params.distinguished_name.push(DnType::CommonName, "WaylandConnect Device CA");
+        params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::DigitalSignature];
+        params.key_pair = key_pair;
+        params
+    }
+
+    /// Signs a new client certificate for `device_id`.
+    pub fn issue(&self, device_id: &str, device_name: &str) -> anyhow::Result<IssuedCertificate> {
+        let mut params = CertificateParams::default();
+        params.distinguished_name = DistinguishedName::new();
+        params.distinguished_name.push(DnType::CommonName, device_id);
+        params.distinguished_name.push(DnType::OrganizationName, device_name);
         params.is_ca = IsCa::NoCa;
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        params.use_authority_key_identifier_extension = true;
 
         let cert = Certificate::from_params(params)?;
-        let cert_pem = cert.serialize_pem()?;
-        let key_pem = cert.serialize_private_key_pem();
-
-        fs::write(&cert_path, &cert_pem)?;
-        fs::write(&key_path, &key_pem)?;
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&key_path)?.permissions();
-            perms.set_mode(0o600); // Read/Write only for owner
-            fs::set_permissions(&key_path, perms)?;
-            info!("🔒 Set strict permissions (600) on private key");
+        let der = cert.serialize_der_with_signer(&self.cert)?;
+        Ok(IssuedCertificate {
+            cert_pem: cert.serialize_pem_with_signer(&self.cert)?,
+            key_pem: cert.serialize_private_key_pem(),
+            fingerprint: certificate_fingerprint(&der),
+        })
+    }
+
+    fn roots(&self) -> anyhow::Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        roots.add(&RustlsCert(self.cert_der.clone()))
+            .map_err(|e| anyhow::anyhow!("Invalid device CA: {}", e))?;
+        Ok(roots)
+    }
+}
+
+/// SHA-256 of a DER certificate, formatted the way clients pin it.
+pub fn certificate_fingerprint(der: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(der);
+    let result = hasher.finalize();
+    result.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+}
+
+fn write_private(path: &Path, contents: &str) -> anyhow::Result<()> {
+    fs::write(path, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o600); // Read/Write only for owner
+        fs::set_permissions(path, perms)?;
+        info!("🔒 Set strict permissions (600) on {:?}", path.file_name().unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Server certificate files in the config dir. `next-*` and `rotation.json`
+/// only exist while a rotation waits for its switch time.
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+const NEXT_CERT_FILE: &str = "next-cert.pem";
+const NEXT_KEY_FILE: &str = "next-key.pem";
+const ROTATION_FILE: &str = "rotation.json";
+
+/// Signature schemes a rotation announcement may use, in order of preference.
+const ROTATION_SCHEMES: &[SignatureScheme] = &[
+    SignatureScheme::ECDSA_NISTP256_SHA256,
+    SignatureScheme::ECDSA_NISTP384_SHA384,
+    SignatureScheme::ED25519,
+    SignatureScheme::RSA_PSS_SHA256,
+];
+
+/// A rotation waiting for its switch time, as kept in `rotation.json`.
+#[derive(Serialize, Deserialize, Clone)]
+struct PendingRotation {
+    old_fingerprint: String,
+    new_fingerprint: String,
+    switch_at_ms: u64,
+    scheme: String,
+    signature: String,
+}
+
+impl PendingRotation {
+    fn announcement(&self) -> ControlResponse {
+        ControlResponse::CertificateRotation {
+            old_fingerprint: self.old_fingerprint.clone(),
+            new_fingerprint: self.new_fingerprint.clone(),
+            switch_at_ms: self.switch_at_ms,
+            scheme: self.scheme.clone(),
+            signature: self.signature.clone(),
         }
-        
-        let mut cert_reader = std::io::BufReader::new(cert_pem.as_bytes());
-        let certs = rustls_pemfile::certs(&mut cert_reader)?
-            .into_iter()
-            .map(RustlsCert)
-            .collect::<Vec<_>>();
-            
-        let mut key_reader = std::io::BufReader::new(key_pem.as_bytes());
-        let keys = rustls_pemfile::pkcs8_private_keys(&mut key_reader)?;
-        
-        (certs, RustlsKey(keys[0].clone()))
-    };
+    }
+}
+
+struct Identity {
+    current: Arc<CertifiedKey>,
+    fingerprint: String,
+    next: Option<(Arc<CertifiedKey>, PendingRotation)>,
+}
+
+/// The certificate the server presents, replaceable while it runs.
+///
+/// `rotate` stores a new key pair as `next-cert.pem`/`next-key.pem` and keeps
+/// presenting the current certificate until the grace period is over. Paired
+/// devices meanwhile get a `CertificateRotation` signed with the current key,
+/// so they know which fingerprint to pin once the switch happens. At the
+/// switch the new files replace `cert.pem`/`key.pem` and the old key is gone.
+pub struct ServerIdentity {
+    config_dir: PathBuf,
+    state: RwLock<Identity>,
+}
+
+impl ServerIdentity {
+    pub fn load_or_create(config_dir: &Path) -> anyhow::Result<Self> {
+        let cert_path = config_dir.join(CERT_FILE);
+        let key_path = config_dir.join(KEY_FILE);
+
+        if !(cert_path.exists() && key_path.exists()) {
+            info!("✨ Generating new self-signed TLS certificates...");
+            let (cert_pem, key_pem) = generate_server_cert()?;
+            fs::write(&cert_path, cert_pem)?;
+            write_private(&key_path, &key_pem)?;
+        } else {
+            info!("📜 Loading existing TLS certificates from {:?}", config_dir);
+        }
+        let current = load_certified_key(&cert_path, &key_path)?;
+
+        let pending = fs::read_to_string(config_dir.join(ROTATION_FILE)).ok()
+            .and_then(|json| serde_json::from_str::<PendingRotation>(&json).ok());
+        let next = match pending {
+            Some(pending) => {
+                let next = load_certified_key(&config_dir.join(NEXT_CERT_FILE), &config_dir.join(NEXT_KEY_FILE))?;
+                info!("🔄 Certificate rotation pending, switching to {}", pending.new_fingerprint);
+                Some((next, pending))
+            }
+            None => None,
+        };
+
+        let identity = Self {
+            config_dir: config_dir.to_path_buf(),
+            state: RwLock::new(Identity {
+                fingerprint: certificate_fingerprint(&current.cert[0].0),
+                current,
+                next,
+            }),
+        };
+        identity.switch_if_due();
+        Ok(identity)
+    }
+
+    /// Fingerprint of the certificate presented right now.
+    pub fn fingerprint(&self) -> String {
+        self.switch_if_due();
+        self.state.read().unwrap().fingerprint.clone()
+    }
+
+    /// The announcement for the rotation in progress, if any.
+    pub fn pending_rotation(&self) -> Option<ControlResponse> {
+        self.switch_if_due();
+        self.state.read().unwrap().next.as_ref().map(|(_, pending)| pending.announcement())
+    }
+
+    /// Generates a new key pair to be presented after `grace`, replacing any
+    /// rotation still pending, and returns the signed announcement.
+    pub fn rotate(&self, grace: Duration) -> anyhow::Result<ControlResponse> {
+        self.switch_if_due();
+        let (cert_pem, key_pem) = generate_server_cert()?;
+        fs::write(self.config_dir.join(NEXT_CERT_FILE), cert_pem)?;
+        write_private(&self.config_dir.join(NEXT_KEY_FILE), &key_pem)?;
+        let next = load_certified_key(&self.config_dir.join(NEXT_CERT_FILE), &self.config_dir.join(NEXT_KEY_FILE))?;
+
+        let mut state = self.state.write().unwrap();
+        let new_fingerprint = certificate_fingerprint(&next.cert[0].0);
+        let switch_at_ms = unix_millis(SystemTime::now() + grace);
+        let signer = state.current.key.choose_scheme(ROTATION_SCHEMES)
+            .ok_or_else(|| anyhow::anyhow!("The server key cannot sign with any rotation scheme"))?;
+        let statement = wc_protocol::rotation_statement(&state.fingerprint, &new_fingerprint, switch_at_ms);
+        let signature = signer.sign(&statement)?;
+        let pending = PendingRotation {
+            old_fingerprint: state.fingerprint.clone(),
+            new_fingerprint,
+            switch_at_ms,
+            scheme: format!("{:?}", signer.scheme()),
+            signature: signature.iter().map(|b| format!("{:02x}", b)).collect(),
+        };
+        fs::write(self.config_dir.join(ROTATION_FILE), serde_json::to_string_pretty(&pending)?)?;
+        info!("🔄 Certificate rotation to {} scheduled in {}s", pending.new_fingerprint, grace.as_secs());
+
+        let announcement = pending.announcement();
+        state.next = Some((next, pending));
+        drop(state);
+        self.switch_if_due();
+        Ok(announcement)
+    }
+
+    fn current(&self) -> Arc<CertifiedKey> {
+        self.switch_if_due();
+        self.state.read().unwrap().current.clone()
+    }
+
+    /// Starts presenting the next certificate once its switch time passed.
+    fn switch_if_due(&self) {
+        let now = unix_millis(SystemTime::now());
+        let due = |state: &Identity| state.next.as_ref().is_some_and(|(_, p)| p.switch_at_ms <= now);
+        if !due(&self.state.read().unwrap()) {
+            return;
+        }
+        let mut state = self.state.write().unwrap();
+        if !due(&state) {
+            return;
+        }
+        let Some((next, pending)) = state.next.take() else { return };
+
+        let dir = &self.config_dir;
+        let result = fs::rename(dir.join(NEXT_CERT_FILE), dir.join(CERT_FILE))
+            .and_then(|_| fs::rename(dir.join(NEXT_KEY_FILE), dir.join(KEY_FILE)))
+            .and_then(|_| fs::remove_file(dir.join(ROTATION_FILE)));
+        if let Err(e) = result {
+            error!("❌ Failed to store the rotated certificate in {:?}: {}", dir, e);
+        }
+        info!("🔄 Now presenting certificate {}", pending.new_fingerprint);
+        state.current = next;
+        state.fingerprint = pending.new_fingerprint;
+    }
+}
+
+impl ResolvesServerCert for ServerIdentity {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+/// A fresh self-signed server certificate and its key, as PEM.
+fn generate_server_cert() -> anyhow::Result<(String, String)> {
+    let mut params = CertificateParams::default();
+    params.not_before = rcgen::date_time_ymd(2023, 1, 1);
+    params.not_after = rcgen::date_time_ymd(2033, 1, 1);
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, "WaylandConnect Backend");
+    params.subject_alt_names = vec![SanType::DnsName("localhost".to_string())];
+    params.is_ca = IsCa::NoCa;
+
+    let cert = Certificate::from_params(params)?;
+    Ok((cert.serialize_pem()?, cert.serialize_private_key_pem()))
+}
 
-    let fingerprint = {
-        let mut hasher = Sha256::new();
-        hasher.update(&cert_chain[0].0);
-        let result = hasher.finalize();
-        result.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> anyhow::Result<Arc<CertifiedKey>> {
+    let cert_data = fs::read(cert_path)?;
+    let key_data = fs::read(key_path)?;
+
+    let mut cert_reader = std::io::BufReader::new(&cert_data[..]);
+    let certs = rustls_pemfile::certs(&mut cert_reader)?
+        .into_iter()
+        .map(RustlsCert)
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        anyhow::bail!("No certificate found in {:?}", cert_path);
+    }
+
+    let mut key_reader = std::io::BufReader::new(&key_data[..]);
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut key_reader)?;
+    if keys.is_empty() {
+        // Try RSA keys if PKCS8 is empty
+        key_reader = std::io::BufReader::new(&key_data[..]);
+        keys = rustls_pemfile::rsa_private_keys(&mut key_reader)?;
+    }
+    let Some(key) = keys.into_iter().next() else {
+        anyhow::bail!("No private key found in {:?}", key_path);
     };
+    let key = any_supported_type(&RustlsKey(key))
+        .map_err(|e| anyhow::anyhow!("Unsupported key in {:?}: {}", key_path, e))?;
+    Ok(Arc::new(CertifiedKey::new(certs, key)))
+}
+
+pub fn load_tls_config(config_dir: &Path) -> anyhow::Result<(Arc<ServerConfig>, Arc<ServerIdentity>, Arc<DeviceCa>)> {
+    if !config_dir.exists() {
+        fs::create_dir_all(config_dir)?;
+    }
+    let identity = Arc::new(ServerIdentity::load_or_create(config_dir)?);
+
+    // Client certificates are optional at the TLS layer because a device has
+    // none until its first pairing is approved. The handshake still rejects
+    // any certificate that was not signed by our CA.
+    let ca = DeviceCa::load_or_create(config_dir)?;
+    let verifier = AllowAnyAnonymousOrAuthenticatedClient::new(ca.roots()?).boxed();
 
     let config = ServerConfig::builder()
         .with_safe_defaults()
-        .with_no_client_auth()
-        .with_single_cert(cert_chain, key)
-        .map_err(|e| anyhow::anyhow!("TLS config error: {}", e))?;
+        .with_client_cert_verifier(verifier)
+        .with_cert_resolver(identity.clone());
 
-    Ok((Arc::new(config), fingerprint))
+    Ok((Arc::new(config), identity, Arc::new(ca)))
 }
@@ -0,0 +1,141 @@
+//! Multitouch bookkeeping for the virtual touch devices: which phone finger
+//! sits in which MT slot, the type-B events a `TouchFrame` becomes, and
+//! where on the desktop a touch on the mirrored screen lands.
+
+use input_linux::{AbsoluteAxis, EventKind, Key};
+use wc_protocol::{MonitorInfo, TouchContact};
+
+/// Fingers tracked at once; more in one frame is refused.
+pub const MAX_CONTACTS: usize = 5;
+/// Tracking ids wrap here, the top of `ABS_MT_TRACKING_ID`'s range.
+pub const MAX_TRACKING_ID: i32 = 0xffff;
+
+/// Tool buttons by finger count, so libinput knows how many are down.
+const TOOLS: [Key; MAX_CONTACTS] = [
+    Key::ButtonToolFinger,
+    Key::ButtonToolDoubleTap,
+    Key::ButtonToolTripleTap,
+    Key::ButtonToolQuadtap,
+    Key::ButtonToolQuintTap,
+];
+
+/// An event as (type, code, value), the way `write_raw` takes it.
+pub type RawEvent = (u16, u16, i32);
+
+/// A finger down in a slot.
+struct Finger {
+    /// Sender of the frames it came in, see `InputAdapter::send_event`
+    source: String,
+    id: u32,
+    x: i32,
+    y: i32,
+}
+
+/// Slots shared by every source; each source's frames only move and lift
+/// its own fingers, whose contact ids are its own.
+#[derive(Default)]
+pub struct MultitouchSlots {
+    slots: [Option<Finger>; MAX_CONTACTS],
+    next_tracking_id: i32,
+}
+
+impl MultitouchSlots {
+    /// Events for one frame from `source`, positions scaled to `0..=max_x`
+    /// and `0..=max_y`, without the closing `SYN_REPORT`. Contacts past
+    /// `MAX_CONTACTS` or the free slots are ignored.
+    pub fn frame(&mut self, source: &str, contacts: &[TouchContact], max_x: i32, max_y: i32) -> Vec<RawEvent> {
+        let contacts = &contacts[..contacts.len().min(MAX_CONTACTS)];
+        let mut events = Vec::new();
+
+        for (slot, state) in self.slots.iter_mut().enumerate() {
+            if state.as_ref().is_some_and(|f| f.source == source && !contacts.iter().any(|c| c.id == f.id)) {
+                *state = None;
+                events.push(abs(AbsoluteAxis::MultitouchSlot, slot as i32));
+                events.push(abs(AbsoluteAxis::MultitouchTrackingId, -1));
+            }
+        }
+
+        for contact in contacts {
+            let x = scale(contact.x, max_x);
+            let y = scale(contact.y, max_y);
+            let existing = self.slots.iter().position(|s| s.as_ref().is_some_and(|f| f.source == source && f.id == contact.id));
+            let Some(slot) = existing.or_else(|| self.slots.iter().position(Option::is_none)) else { continue };
+            events.push(abs(AbsoluteAxis::MultitouchSlot, slot as i32));
+            if existing.is_none() {
+                events.push(abs(AbsoluteAxis::MultitouchTrackingId, self.next_tracking_id));
+                self.next_tracking_id = (self.next_tracking_id + 1) % (MAX_TRACKING_ID + 1);
+            }
+            events.push(abs(AbsoluteAxis::MultitouchPositionX, x));
+            events.push(abs(AbsoluteAxis::MultitouchPositionY, y));
+            self.slots[slot] = Some(Finger { source: source.to_string(), id: contact.id, x, y });
+        }
+
+        // Single-touch view for older consumers: touch state, finger count
+        // and the first finger's position
+        let down = self.slots.iter().flatten().count();
+        events.push(key(Key::ButtonTouch, (down > 0) as i32));
+        for (i, tool) in TOOLS.iter().enumerate() {
+            events.push(key(*tool, (down == i + 1) as i32));
+        }
+        if let Some(finger) = self.slots.iter().flatten().next() {
+            events.push(abs(AbsoluteAxis::X, finger.x));
+            events.push(abs(AbsoluteAxis::Y, finger.y));
+        }
+        events
+    }
+}
+
+/// The part of the desktop one monitor covers, as fractions of the bounding
+/// box of all monitors, which is the space the virtual touchscreen spans.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenArea {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ScreenArea {
+    pub const WHOLE: ScreenArea = ScreenArea { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    /// Where monitor `id` sits in the layout of `monitors`, in logical
+    /// pixels; the whole desktop when it isn't one of them, e.g. without
+    /// Hyprland.
+    pub fn of_monitor(monitors: &[MonitorInfo], id: i32) -> Self {
+        let Some(monitor) = monitors.iter().find(|m| m.id == id) else { return Self::WHOLE };
+        let right_of = |m: &MonitorInfo| m.x as f64 + m.logical_size().0;
+        let bottom_of = |m: &MonitorInfo| m.y as f64 + m.logical_size().1;
+        let left = monitors.iter().map(|m| m.x as f64).fold(f64::INFINITY, f64::min);
+        let top = monitors.iter().map(|m| m.y as f64).fold(f64::INFINITY, f64::min);
+        let right = monitors.iter().map(right_of).fold(f64::NEG_INFINITY, f64::max);
+        let bottom = monitors.iter().map(bottom_of).fold(f64::NEG_INFINITY, f64::max);
+        if right <= left || bottom <= top {
+            return Self::WHOLE;
+        }
+        let (width, height) = (right - left, bottom - top);
+        let (monitor_width, monitor_height) = monitor.logical_size();
+        Self {
+            x: (monitor.x as f64 - left) / width,
+            y: (monitor.y as f64 - top) / height,
+            width: monitor_width / width,
+            height: monitor_height / height,
+        }
+    }
+
+    /// A point 0.0-1.0 across the monitor, as 0.0-1.0 across the desktop.
+    pub fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.x + x.clamp(0.0, 1.0) * self.width, self.y + y.clamp(0.0, 1.0) * self.height)
+    }
+}
+
+fn scale(value: f64, max: i32) -> i32 {
+    (value.clamp(0.0, 1.0) * max as f64).round() as i32
+}
+
+fn abs(axis: AbsoluteAxis, value: i32) -> RawEvent {
+    (EventKind::Absolute as u16, axis as u16, value)
+}
+
+fn key(key: Key, value: i32) -> RawEvent {
+    (EventKind::Key as u16, key as u16, value)
+}
@@ -0,0 +1,157 @@
+//! Keyboard layouts through libxkbcommon, loaded at runtime, to find which
+//! key and modifiers produce a character on the desktop's active layout.
+
+use std::ffi::CString;
+use input_linux::Key;
+use xkbcommon_dl::{
+    xkb_context, xkb_context_flags, xkb_keymap, xkb_keymap_compile_flags, xkb_keymap_format,
+    xkb_state, xkbcommon_option, XkbCommon, XKB_MOD_INVALID, XKB_MOD_NAME_SHIFT,
+};
+use xkeysym::Keysym;
+
+/// Offset between XKB keycodes and evdev codes.
+const EVDEV_OFFSET: u32 = 8;
+/// The real modifier AltGr (ISO_Level3_Shift) sets in the stock layouts.
+const LEVEL3_MOD_NAME: &[u8] = b"Mod5\0";
+
+/// How to type one character on the uinput keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stroke {
+    /// evdev key code
+    pub key: u16,
+    pub shift: bool,
+    /// Hold AltGr
+    pub level3: bool,
+}
+
+/// The keysym a key must produce to type `c`.
+pub fn keysym_for(c: char) -> Keysym {
+    match c {
+        '\n' => Keysym::Return,
+        c => Keysym::from_char(c),
+    }
+}
+
+pub struct Keymap {
+    xkb: &'static XkbCommon,
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+    shift_mask: u32,
+    level3_mask: u32,
+}
+
+// The pointers are owned by this value and only used through `&mut self`
+unsafe impl Send for Keymap {}
+
+impl Keymap {
+    /// Compiles a keymap in XKB text format, e.g. from `wl_keyboard.keymap`.
+    pub fn from_string(text: &str) -> Option<Self> {
+        let xkb = xkbcommon_option()?;
+        let text = CString::new(text.trim_end_matches('\0')).ok()?;
+        unsafe {
+            let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let keymap = (xkb.xkb_keymap_new_from_string)(
+                context,
+                text.as_ptr(),
+                xkb_keymap_format::XKB_KEYMAP_FORMAT_TEXT_V1,
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            Self::new(xkb, context, keymap)
+        }
+    }
+
+    /// The layout libxkbcommon picks from `XKB_DEFAULT_LAYOUT` and friends,
+    /// for when the compositor can't be asked.
+    pub fn from_environment() -> Option<Self> {
+        let xkb = xkbcommon_option()?;
+        unsafe {
+            let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let keymap = (xkb.xkb_keymap_new_from_names)(
+                context,
+                std::ptr::null(),
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            Self::new(xkb, context, keymap)
+        }
+    }
+
+    unsafe fn new(xkb: &'static XkbCommon, context: *mut xkb_context, keymap: *mut xkb_keymap) -> Option<Self> {
+        if keymap.is_null() {
+            (xkb.xkb_context_unref)(context);
+            return None;
+        }
+        let state = (xkb.xkb_state_new)(keymap);
+        if state.is_null() {
+            (xkb.xkb_keymap_unref)(keymap);
+            (xkb.xkb_context_unref)(context);
+            return None;
+        }
+        let mask = |name: &[u8]| match (xkb.xkb_keymap_mod_get_index)(keymap, name.as_ptr().cast()) {
+            XKB_MOD_INVALID => 0,
+            index => 1 << index,
+        };
+        let shift_mask = mask(XKB_MOD_NAME_SHIFT);
+        let level3_mask = mask(LEVEL3_MOD_NAME);
+        Some(Self { xkb, context, keymap, state, shift_mask, level3_mask })
+    }
+
+    /// The key and modifiers that type `c` in layout `group`, preferring the
+    /// fewest modifiers. `None` when no key on the layout produces it.
+    pub fn resolve(&mut self, c: char, group: u32) -> Option<Stroke> {
+        let keysym = keysym_for(c);
+        if keysym == Keysym::NoSymbol {
+            return None;
+        }
+
+        let candidates = [
+            (false, false),
+            (true, false),
+            (false, true),
+            (true, true),
+        ];
+        unsafe {
+            let min = (self.xkb.xkb_keymap_min_keycode)(self.keymap).max(EVDEV_OFFSET);
+            let max = (self.xkb.xkb_keymap_max_keycode)(self.keymap);
+            // Keys on a regular keyboard first, so e.g. AltGr+E beats KEY_EURO
+            let regular = min..=max.min(EVDEV_OFFSET + 0xff);
+            let extended = (EVDEV_OFFSET + 0x100).max(min)..=max;
+            for keycodes in [regular, extended] {
+                for (shift, level3) in candidates {
+                    if (shift && self.shift_mask == 0) || (level3 && self.level3_mask == 0) {
+                        continue;
+                    }
+                    let mods = if shift { self.shift_mask } else { 0 } | if level3 { self.level3_mask } else { 0 };
+                    (self.xkb.xkb_state_update_mask)(self.state, mods, 0, 0, 0, 0, group);
+                    for keycode in keycodes.clone() {
+                        let Ok(key) = u16::try_from(keycode - EVDEV_OFFSET) else { break };
+                        // Only keys the uinput keyboard can press
+                        if !Key::from_code(key).is_ok_and(|k| k.is_key()) {
+                            continue;
+                        }
+                        if (self.xkb.xkb_state_key_get_one_sym)(self.state, keycode) == keysym.raw() {
+                            return Some(Stroke { key, shift, level3 });
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xkb.xkb_state_unref)(self.state);
+            (self.xkb.xkb_keymap_unref)(self.keymap);
+            (self.xkb.xkb_context_unref)(self.context);
+        }
+    }
+}
@@ -0,0 +1,583 @@
+//! Drives a real server over TLS and the control socket, with a recording
+//! input adapter and fake desktop services, so no desktop session is needed.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_trait::async_trait;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::Receiver;
+use wayland_connect_backend::adapter::InputAdapter;
+use wayland_connect_backend::fakes::Fakes;
+use wayland_connect_backend::limits::MAX_CONNECTIONS_PER_IP;
+use wayland_connect_backend::screen_streamer::ScreenStreamer;
+use wayland_connect_backend::server::{InputServer, ServerOptions};
+use wc_client::wc_protocol::{capability, AppInfo, AuditAction, ControlResponse, DashboardEvent, ErrorCode, InputEvent, MediaMetadata, MonitorInfo, Permission, TouchContact};
+use wc_client::{Client, ClientError, Pairing, ServerEvent};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct RecordingAdapter {
+    events: Mutex<Vec<InputEvent>>,
+}
+
+#[async_trait]
+impl InputAdapter for RecordingAdapter {
+    async fn send_event(&self, _source: &str, event: InputEvent) -> anyhow::Result<()> {
+        self.events.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+impl RecordingAdapter {
+    fn take(&self) -> Vec<InputEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+struct TestServer {
+    addr: String,
+    socket: PathBuf,
+    dir: TempDir,
+    adapter: Arc<RecordingAdapter>,
+    fakes: Fakes,
+}
+
+impl TestServer {
+    async fn start() -> Self {
+        Self::start_with_devices(None).await
+    }
+
+    /// A server whose `devices.json` already holds `devices`.
+    async fn start_with_devices(devices: Option<&str>) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        if let Some(devices) = devices {
+            std::fs::create_dir_all(dir.path().join("config")).unwrap();
+            std::fs::write(dir.path().join("config").join("devices.json"), devices).unwrap();
+        }
+        let socket = dir.path().join("control.sock");
+        let adapter = Arc::new(RecordingAdapter::default());
+        let options = ServerOptions {
+            config_dir: dir.path().join("config"),
+            control_socket: Some(socket.clone()),
+            media_addr: None,
+            discovery_addr: None,
+            server_name: "test-desktop".to_string(),
+        };
+        let fakes = Fakes::default();
+        let server = InputServer::with_parts(adapter.clone(), fakes.services(), ScreenStreamer::new(), options);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { server.serve(listener).await });
+        Self { addr, socket, dir, adapter, fakes }
+    }
+
+    async fn device(&self) -> Client {
+        Client::connect(&self.addr, None).await.unwrap()
+    }
+
+    /// A registered dashboard on the control socket, once the server opened it.
+    async fn dashboard(&self) -> Client {
+        let client = tokio::time::timeout(WAIT, async {
+            loop {
+                match Client::connect_local(&self.socket).await {
+                    Ok(client) => break client,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        }).await.expect("control socket never came up");
+
+        let token_path = self.dir.path().join("config").join(wc_core::constants::DASHBOARD_TOKEN_FILE);
+        let token = std::fs::read_to_string(token_path).unwrap();
+        client.register_dashboard(token.trim()).await.unwrap();
+        client
+    }
+}
+
+/// Waits for the first response `pick` accepts, skipping everything else.
+async fn expect<T>(events: &mut Receiver<ServerEvent>, pick: impl Fn(ControlResponse) -> Option<T>) -> T {
+    tokio::time::timeout(WAIT, async {
+        loop {
+            match events.recv().await {
+                Some(ServerEvent::Control(response)) => {
+                    if let Some(found) = pick(response) {
+                        return found;
+                    }
+                }
+                Some(ServerEvent::Binary(_)) => {}
+                None => panic!("connection closed"),
+            }
+        }
+    }).await.expect("timed out waiting for a response")
+}
+
+fn security_update(response: ControlResponse) -> Option<String> {
+    match response {
+        ControlResponse::SecurityUpdate { status } => Some(status),
+        _ => None,
+    }
+}
+
+fn error_code(result: Result<(), ClientError>) -> Option<ErrorCode> {
+    match result {
+        Err(ClientError::Rejected { code, .. }) => code,
+        other => panic!("expected a rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pairing_approval_input_and_blocking() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let device = server.device().await;
+    let mut device_events = device.take_events().unwrap();
+
+    let paired = device.pair(Pairing::new("Test phone", "phone-1")).await.unwrap();
+    assert_eq!(paired.status, "Pending");
+    assert_eq!(error_code(device.move_by(1.0, 2.0).await), Some(ErrorCode::NotTrusted));
+
+    // Management needs the dashboard, not just a paired device
+    let result = device.request(InputEvent::ApproveDevice { id: "phone-1".to_string() }).await.map(drop);
+    assert_eq!(error_code(result), Some(ErrorCode::NotDashboard));
+
+    dashboard.request(InputEvent::ApproveDevice { id: "phone-1".to_string() }).await.unwrap();
+    assert_eq!(expect(&mut device_events, security_update).await, "Trusted");
+    expect(&mut device_events, |r| matches!(r, ControlResponse::ClientCertificate { .. }).then_some(())).await;
+
+    device.move_by(1.0, 2.0).await.unwrap();
+    device.click("left").await.unwrap();
+    device.scroll_by(-0.5, 1.25, true).await.unwrap();
+    let recorded = server.adapter.take();
+    assert!(matches!(recorded.as_slice(), [
+        InputEvent::Move { dx, dy },
+        InputEvent::Click { button },
+        InputEvent::Scroll { dx: sx, dy: sy, precise: true },
+    ] if *dx == 1.0 && *dy == 2.0 && button == "left" && *sx == -0.5 && *sy == 1.25), "recorded {:?}", recorded);
+
+    dashboard.request(InputEvent::BlockDevice { id: "phone-1".to_string() }).await.unwrap();
+    assert_eq!(expect(&mut device_events, security_update).await, "Blocked");
+    assert_eq!(error_code(device.move_by(3.0, 4.0).await), Some(ErrorCode::NotTrusted));
+    assert!(server.adapter.take().is_empty());
+
+    let devices = dashboard.status().await.unwrap();
+    let blocked = devices.iter().find(|d| d.id == "phone-1").unwrap();
+    assert_eq!(blocked.status, "Blocked");
+    assert_eq!(blocked.cert_fingerprint, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_device_is_reported() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+
+    let result = dashboard.request(InputEvent::ApproveDevice { id: "nobody".to_string() }).await.map(drop);
+    assert_eq!(error_code(result), Some(ErrorCode::UnknownDevice));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disconnect_removes_pending_device() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let mut dashboard_events = dashboard.take_events().unwrap();
+    dashboard.subscribe_events().await.unwrap();
+
+    let device = server.device().await;
+    assert_eq!(device.pair(Pairing::new("Drive-by", "phone-2")).await.unwrap().status, "Pending");
+    let dashboard_event = |r: ControlResponse| match r {
+        ControlResponse::DashboardEvent { event } => Some(event),
+        _ => None,
+    };
+    let connected = expect(&mut dashboard_events, dashboard_event).await;
+    assert!(matches!(connected, DashboardEvent::DeviceConnected { ref id, .. } if id == "phone-2"), "got {:?}", connected);
+    assert!(dashboard.status().await.unwrap().iter().any(|d| d.id == "phone-2"));
+
+    device.close().await.unwrap();
+    expect(&mut dashboard_events, |r| match dashboard_event(r) {
+        Some(DashboardEvent::DeviceDisconnected { id }) if id == "phone-2" => Some(()),
+        _ => None,
+    }).await;
+    expect(&mut dashboard_events, |r| match dashboard_event(r) {
+        Some(DashboardEvent::DeviceRemoved { id }) if id == "phone-2" => Some(()),
+        _ => None,
+    }).await;
+    assert!(!dashboard.status().await.unwrap().iter().any(|d| d.id == "phone-2"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn legacy_trusted_device_is_approved_again() {
+    let server = TestServer::start_with_devices(Some(r#"{"devices": {"phone-old": {
+        "id": "phone-old", "name": "Old phone", "status": "Trusted", "ip": "127.0.0.1", "auto_reconnect": true
+    }}}"#)).await;
+    let device = server.device().await;
+    let mut device_events = device.take_events().unwrap();
+
+    // Claiming the id is not enough to get trust or a certificate
+    assert_eq!(device.pair(Pairing::new("Old phone", "phone-old")).await.unwrap().status, "Pending");
+    assert_eq!(error_code(device.move_by(1.0, 1.0).await), Some(ErrorCode::NotTrusted));
+
+    assert!(server.fakes.notifier.answer("New Connection Request", true));
+    assert_eq!(expect(&mut device_events, security_update).await, "Trusted");
+    expect(&mut device_events, |r| matches!(r, ControlResponse::ClientCertificate { .. }).then_some(())).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unverified_id_is_held_by_one_connection() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let device = server.device().await;
+    assert_eq!(device.pair(Pairing::new("Test phone", "phone-twin")).await.unwrap().status, "Pending");
+
+    // A second connection claiming the same id can't ride along on the approval
+    let impostor = server.device().await;
+    assert_eq!(impostor.pair(Pairing::new("Test phone", "phone-twin")).await.unwrap().status, "Rejected");
+    dashboard.request(InputEvent::ApproveDevice { id: "phone-twin".to_string() }).await.unwrap();
+    assert_eq!(error_code(impostor.move_by(1.0, 1.0).await), Some(ErrorCode::NotTrusted));
+    device.move_by(1.0, 1.0).await.unwrap();
+    assert_eq!(server.adapter.take().len(), 1);
+}
+
+/// A paired, approved device and its event stream.
+async fn trusted_device(server: &TestServer, dashboard: &Client, id: &str) -> (Client, Receiver<ServerEvent>) {
+    let device = server.device().await;
+    let mut events = device.take_events().unwrap();
+    assert_eq!(device.pair(Pairing::new("Test phone", id)).await.unwrap().status, "Pending");
+    dashboard.request(InputEvent::ApproveDevice { id: id.to_string() }).await.unwrap();
+    assert_eq!(expect(&mut events, security_update).await, "Trusted");
+    (device, events)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyboard_chords_and_held_keys() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let (device, _events) = trusted_device(&server, &dashboard, "phone-kb").await;
+
+    device.key_combo(&["ctrl", "Shift_L"], "t").await.unwrap();
+    device.key_down("Alt").await.unwrap();
+    device.key_press("F11").await.unwrap();
+    device.key_up("KEY_LEFTALT").await.unwrap();
+    device.key_down("super").await.unwrap();
+    assert_eq!(error_code(device.key_press("NoSuchKey").await), Some(ErrorCode::InvalidRequest));
+    assert_eq!(error_code(device.key_combo(&["ctrl", "Hyper-ish"], "c").await), Some(ErrorCode::InvalidRequest));
+
+    let recorded = server.adapter.take();
+    assert!(matches!(recorded.as_slice(), [
+        InputEvent::KeyCombo { modifiers, key: combo_key },
+        InputEvent::KeyDown { key: down },
+        InputEvent::KeyPress { key: pressed },
+        InputEvent::KeyUp { key: up },
+        InputEvent::KeyDown { key: held },
+    ] if modifiers == &["LeftCtrl", "LeftShift"] && combo_key == "T" && down == "LeftAlt"
+        && pressed == "F11" && up == "LeftAlt" && held == "LeftMeta"), "recorded {:?}", recorded);
+
+    device.type_text("Grüße, 世界 🙂").await.unwrap();
+    let too_long = "x".repeat(5000);
+    assert_eq!(error_code(device.type_text(&too_long).await), Some(ErrorCode::InvalidRequest));
+    let recorded = server.adapter.take();
+    assert!(matches!(recorded.as_slice(), [InputEvent::TypeText { text }] if text == "Grüße, 世界 🙂"), "recorded {:?}", recorded);
+
+    // Keys still down when the device goes away are released for it
+    device.close().await.unwrap();
+    let deadline = tokio::time::Instant::now() + WAIT;
+    let released = loop {
+        let recorded = server.adapter.take();
+        if !recorded.is_empty() || tokio::time::Instant::now() > deadline {
+            break recorded;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert!(matches!(released.as_slice(), [InputEvent::KeyUp { key }] if key == "LeftMeta"), "released {:?}", released);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn touch_frames_and_lifted_fingers() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let (device, _events) = trusted_device(&server, &dashboard, "phone-touch").await;
+
+    let two = [TouchContact { id: 1, x: 0.2, y: 0.3 }, TouchContact { id: 2, x: 0.6, y: 0.3 }];
+    device.touch_frame(&two).await.unwrap();
+    let six: Vec<_> = (0..6).map(|id| TouchContact { id, x: 0.5, y: 0.5 }).collect();
+    assert_eq!(error_code(device.touch_frame(&six).await), Some(ErrorCode::InvalidRequest));
+    let recorded = server.adapter.take();
+    assert!(matches!(recorded.as_slice(), [InputEvent::TouchFrame { contacts }] if contacts == &two), "recorded {:?}", recorded);
+
+    // Fingers still down when the device goes away are lifted for it
+    device.close().await.unwrap();
+    let deadline = tokio::time::Instant::now() + WAIT;
+    let released = loop {
+        let recorded = server.adapter.take();
+        if !recorded.is_empty() || tokio::time::Instant::now() > deadline {
+            break recorded;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert!(matches!(released.as_slice(), [InputEvent::TouchFrame { contacts }] if contacts.is_empty()), "released {:?}", released);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn screen_touches_land_on_the_mirrored_monitor() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let (device, _events) = trusted_device(&server, &dashboard, "phone-screen").await;
+    let monitor = |id, name: &str, x| MonitorInfo { id, name: name.to_string(), width: 1920, height: 1080, x, y: 0, focused: id == 0, scale: 1.0, transform: 0 };
+    server.fakes.monitors.set_monitors(vec![monitor(0, "eDP-1", 0), monitor(1, "DP-1", 1920)]);
+
+    // Only the device mirroring a screen has one to touch
+    assert_eq!(error_code(device.screen_touch(0.5, 0.5, "down").await), Some(ErrorCode::InvalidRequest));
+    device.start_mirroring(854, 480, 15, 1).await.unwrap();
+    dashboard.request(InputEvent::MirrorResponse { device_id: "phone-screen".to_string(), accepted: true }).await.unwrap();
+
+    // A drag across the right-hand monitor, which is the right half of the desktop
+    device.screen_touch(0.0, 0.5, "down").await.unwrap();
+    device.screen_touch(0.5, 0.25, "move").await.unwrap();
+    device.screen_touch(1.0, 0.25, "up").await.unwrap();
+    assert_eq!(error_code(device.screen_touch(0.5, 0.5, "hover").await), Some(ErrorCode::InvalidRequest));
+    let recorded = server.adapter.take();
+    let touches: Vec<_> = recorded.iter().map(|e| match e {
+        InputEvent::ScreenTouch { x, y, state } => (*x, *y, state.as_str()),
+        other => panic!("recorded {:?}", other),
+    }).collect();
+    assert_eq!(touches, [(0.5, 0.5, "down"), (0.75, 0.25, "move"), (1.0, 0.25, "up")]);
+
+    // A long-press still in progress is lifted when the device goes away
+    device.screen_touch(0.5, 0.5, "down").await.unwrap();
+    assert_eq!(server.adapter.take().len(), 1);
+    device.close().await.unwrap();
+    let deadline = tokio::time::Instant::now() + WAIT;
+    let released = loop {
+        let recorded = server.adapter.take();
+        if !recorded.is_empty() || tokio::time::Instant::now() > deadline {
+            break recorded;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert!(matches!(released.as_slice(), [InputEvent::ScreenTouch { state, .. }] if state == "up"), "released {:?}", released);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn notification_approves_device() {
+    let server = TestServer::start().await;
+    let device = server.device().await;
+    let mut device_events = device.take_events().unwrap();
+
+    let paired = device.pair(Pairing::new("Test phone", "phone-3")).await.unwrap();
+    assert_eq!(paired.status, "Pending");
+    assert_eq!(paired.server_name, "test-desktop");
+    assert!(server.fakes.notifier.answer("New Connection Request", true));
+
+    assert_eq!(expect(&mut device_events, security_update).await, "Trusted");
+    let shown = server.fakes.notifier.shown();
+    assert!(shown.iter().any(|n| n.summary == "Device Paired" && n.body.contains("Test phone")), "shown {:?}", shown);
+    device.move_by(1.0, 1.0).await.unwrap();
+    assert_eq!(server.adapter.take().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn media_and_monitors_come_from_services() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let (device, _events) = trusted_device(&server, &dashboard, "phone-4").await;
+
+    assert!(device.media_status().await.unwrap().is_none());
+    assert_eq!(error_code(device.media_control("next").await), Some(ErrorCode::MediaFailed));
+
+    server.fakes.media.set_metadata(Some(MediaMetadata {
+        title: "Song".to_string(),
+        artist: "Artist".to_string(),
+        album: String::new(),
+        art_url: String::new(),
+        duration: 0,
+        position: 0,
+        status: "Playing".to_string(),
+        player_name: "org.mpris.MediaPlayer2.test".to_string(),
+        shuffle: false,
+        repeat: "None".to_string(),
+        volume: 1.0,
+        track_id: "1".to_string(),
+    }));
+    assert_eq!(device.media_status().await.unwrap().map(|m| m.title), Some("Song".to_string()));
+    device.media_control("next").await.unwrap();
+    assert_eq!(server.fakes.media.commands(), vec!["next".to_string()]);
+
+    server.fakes.monitors.set_monitors(vec![MonitorInfo {
+        id: 0,
+        name: "DP-1".to_string(),
+        width: 2560,
+        height: 1440,
+        x: 0,
+        y: 0,
+        focused: true,
+        scale: 1.0,
+        transform: 0,
+    }]);
+    let monitors = device.monitors().await.unwrap();
+    assert_eq!(monitors.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["DP-1"]);
+
+    // Only catalogued applications may be launched
+    assert_eq!(error_code(device.launch_app("rm -rf /").await), Some(ErrorCode::UnverifiedApp));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn connections_per_address_are_capped() {
+    let server = TestServer::start().await;
+    let mut open = Vec::new();
+    for _ in 0..MAX_CONNECTIONS_PER_IP {
+        open.push(server.device().await);
+    }
+    assert!(Client::connect(&server.addr, None).await.is_err());
+
+    // A closed connection frees its slot
+    open.pop().unwrap().close().await.unwrap();
+    tokio::time::timeout(WAIT, async {
+        while Client::connect(&server.addr, None).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }).await.expect("slot was never released");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pair_requests_are_rate_limited() {
+    let server = TestServer::start().await;
+    let device = server.device().await;
+
+    for _ in 0..5 {
+        assert_eq!(device.pair(Pairing::new("Chatty phone", "phone-5")).await.unwrap().status, "Pending");
+    }
+    assert_eq!(device.pair(Pairing::new("Chatty phone", "phone-5")).await.unwrap().status, "RateLimited");
+
+    // Only the first request asked the desktop
+    let prompts = server.fakes.notifier.shown().iter().filter(|n| n.summary == "New Connection Request").count();
+    assert_eq!(prompts, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prompts_are_rate_limited() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+
+    for i in 0..4 {
+        let device = server.device().await;
+        let id = format!("drive-by-{}", i);
+        assert_eq!(device.pair(Pairing::new("Drive-by", &id)).await.unwrap().status, "Pending");
+    }
+    let prompts = server.fakes.notifier.shown().iter().filter(|n| n.summary == "New Connection Request").count();
+    assert_eq!(prompts, 3);
+
+    // The rest still show up for the dashboard to decide
+    let devices = dashboard.status().await.unwrap();
+    assert_eq!(devices.iter().filter(|d| d.status == "Pending").count(), 4);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mirroring_prompts_are_rate_limited() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let (device, _events) = trusted_device(&server, &dashboard, "phone-mirror").await;
+
+    for _ in 0..4 {
+        device.start_mirroring(854, 480, 15, 0).await.unwrap();
+    }
+    // The pairing prompt took one of the three
+    let prompts = server.fakes.notifier.shown().iter().filter(|n| n.summary == "Screen Share Request").count();
+    assert_eq!(prompts, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn declined_address_cools_down() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+
+    let device = server.device().await;
+    assert_eq!(device.pair(Pairing::new("Test phone", "phone-6")).await.unwrap().status, "Pending");
+    dashboard.request(InputEvent::RejectDevice { id: "phone-6".to_string() }).await.unwrap();
+
+    // A fresh id from the same address doesn't get to ask again
+    let retry = server.device().await;
+    assert_eq!(retry.pair(Pairing::new("Test phone", "phone-7")).await.unwrap().status, "Declined");
+    assert!(!dashboard.status().await.unwrap().iter().any(|d| d.id == "phone-7"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn privileged_actions_are_audited() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    server.fakes.apps.set_apps(vec![AppInfo {
+        name: "True".to_string(),
+        exec: "true".to_string(),
+        icon: String::new(),
+        icon_base64: None,
+    }]);
+
+    let (device, _events) = trusted_device(&server, &dashboard, "phone-8").await;
+    device.launch_app("true").await.unwrap();
+    assert_eq!(error_code(device.launch_app("rm -rf /").await), Some(ErrorCode::UnverifiedApp));
+    let permissions = [Permission::Input].into_iter().collect();
+    dashboard.request(InputEvent::SetDevicePermissions { id: "phone-8".to_string(), permissions }).await.unwrap();
+    assert_eq!(error_code(device.launch_app("true").await), Some(ErrorCode::PermissionDenied));
+    dashboard.request(InputEvent::BlockDevice { id: "phone-8".to_string() }).await.unwrap();
+
+    let (entries, next_before) = dashboard.audit_log(None, 100).await.unwrap();
+    assert_eq!(next_before, None);
+    let summary: Vec<_> = entries.iter().rev()
+        .map(|e| (e.action, e.outcome.as_str(), e.detail.as_deref()))
+        .collect();
+    assert_eq!(summary, [
+        (AuditAction::PairRequest, "Pending", None),
+        (AuditAction::ApproveDevice, "Trusted", Some("dashboard")),
+        (AuditAction::LaunchApp, "Launched", Some("true")),
+        (AuditAction::LaunchApp, "UnverifiedApp", Some("rm -rf /")),
+        (AuditAction::LaunchApp, "PermissionDenied", Some("true")),
+        (AuditAction::BlockDevice, "Blocked", Some("dashboard")),
+    ]);
+    assert!(entries.iter().all(|e| e.device_id == "phone-8" && e.ip.as_deref() == Some("127.0.0.1")));
+
+    // Paging continues below the last entry of the previous page
+    let (first, next_before) = dashboard.audit_log(None, 2).await.unwrap();
+    assert_eq!(next_before, Some(first[1].seq));
+    let (second, _) = dashboard.audit_log(next_before, 2).await.unwrap();
+    assert_eq!(second, entries[2..4]);
+
+    // Devices don't get to read it
+    let result = device.audit_log(None, 10).await.map(drop);
+    assert_eq!(error_code(result), Some(ErrorCode::NotDashboard));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn certificate_rotation_is_announced_and_signed() {
+    let server = TestServer::start().await;
+    let dashboard = server.dashboard().await;
+    let device = server.device().await;
+    let old_fingerprint = device.server_fingerprint().unwrap().to_string();
+    let mut events = device.take_events().unwrap();
+    let pairing = Pairing::new("Test phone", "phone-9").capabilities(&[capability::CERT_ROTATION]);
+    assert_eq!(device.pair(pairing).await.unwrap().status, "Pending");
+    dashboard.request(InputEvent::ApproveDevice { id: "phone-9".to_string() }).await.unwrap();
+
+    // During the grace period the old certificate is still the one presented
+    dashboard.rotate_certificate(3600).await.unwrap();
+    let rotation = expect(&mut events, |r| matches!(r, ControlResponse::CertificateRotation { .. }).then_some(r)).await;
+    let new_fingerprint = device.verify_rotation(&rotation).unwrap();
+    assert_ne!(new_fingerprint, old_fingerprint);
+    assert_eq!(server.device().await.server_fingerprint(), Some(old_fingerprint.as_str()));
+
+    let ControlResponse::CertificateRotation { old_fingerprint: old, switch_at_ms, scheme, signature, .. } = rotation.clone() else { unreachable!() };
+    let forged = ControlResponse::CertificateRotation {
+        old_fingerprint: old,
+        new_fingerprint: "00:11:22".to_string(),
+        switch_at_ms,
+        scheme,
+        signature,
+    };
+    assert!(matches!(device.verify_rotation(&forged), Err(ClientError::InvalidRotation(_))));
+
+    // Without a grace period the new certificate is presented right away
+    let rotation = dashboard.rotate_certificate(0).await.unwrap();
+    let new_fingerprint = device.verify_rotation(&rotation).unwrap();
+    let repinned = Client::connect(&server.addr, Some(&new_fingerprint)).await.unwrap();
+    assert_eq!(repinned.server_fingerprint(), Some(new_fingerprint.as_str()));
+    assert!(Client::connect(&server.addr, Some(&old_fingerprint)).await.is_err());
+}
@@ -0,0 +1,148 @@
+//! Type-B multitouch events produced from `TouchFrame`s, and where touches
+//! on a mirrored monitor land on the desktop.
+
+use input_linux::{AbsoluteAxis, EventKind, Key};
+use wayland_connect_backend::touch::{MultitouchSlots, RawEvent, ScreenArea};
+use wc_protocol::{MonitorInfo, TouchContact};
+
+fn contact(id: u32, x: f64, y: f64) -> TouchContact {
+    TouchContact { id, x, y }
+}
+
+fn abs(axis: AbsoluteAxis, value: i32) -> RawEvent {
+    (EventKind::Absolute as u16, axis as u16, value)
+}
+
+fn key(key: Key, value: i32) -> RawEvent {
+    (EventKind::Key as u16, key as u16, value)
+}
+
+/// BTN_TOUCH and the tool buttons for `fingers` down.
+fn finger_count(fingers: usize) -> Vec<RawEvent> {
+    let tools = [
+        Key::ButtonToolFinger,
+        Key::ButtonToolDoubleTap,
+        Key::ButtonToolTripleTap,
+        Key::ButtonToolQuadtap,
+        Key::ButtonToolQuintTap,
+    ];
+    let mut events = vec![key(Key::ButtonTouch, (fingers > 0) as i32)];
+    events.extend(tools.iter().enumerate().map(|(i, tool)| key(*tool, (fingers == i + 1) as i32)));
+    events
+}
+
+#[test]
+fn fingers_keep_their_slots_until_lifted() {
+    let mut slots = MultitouchSlots::default();
+
+    let mut expected = vec![
+        abs(AbsoluteAxis::MultitouchSlot, 0),
+        abs(AbsoluteAxis::MultitouchTrackingId, 0),
+        abs(AbsoluteAxis::MultitouchPositionX, 250),
+        abs(AbsoluteAxis::MultitouchPositionY, 500),
+    ];
+    expected.extend(finger_count(1));
+    expected.extend([abs(AbsoluteAxis::X, 250), abs(AbsoluteAxis::Y, 500)]);
+    assert_eq!(slots.frame("phone", &[contact(7, 0.25, 0.5)], 1000, 1000), expected);
+
+    // A second finger gets the next slot and tracking id
+    let mut expected = vec![
+        abs(AbsoluteAxis::MultitouchSlot, 0),
+        abs(AbsoluteAxis::MultitouchPositionX, 300),
+        abs(AbsoluteAxis::MultitouchPositionY, 500),
+        abs(AbsoluteAxis::MultitouchSlot, 1),
+        abs(AbsoluteAxis::MultitouchTrackingId, 1),
+        abs(AbsoluteAxis::MultitouchPositionX, 1000),
+        abs(AbsoluteAxis::MultitouchPositionY, 0),
+    ];
+    expected.extend(finger_count(2));
+    expected.extend([abs(AbsoluteAxis::X, 300), abs(AbsoluteAxis::Y, 500)]);
+    assert_eq!(slots.frame("phone", &[contact(7, 0.3, 0.5), contact(9, 1.5, -1.0)], 1000, 1000), expected);
+
+    // Lifting the first leaves the second where it was
+    let mut expected = vec![
+        abs(AbsoluteAxis::MultitouchSlot, 0),
+        abs(AbsoluteAxis::MultitouchTrackingId, -1),
+        abs(AbsoluteAxis::MultitouchSlot, 1),
+        abs(AbsoluteAxis::MultitouchPositionX, 900),
+        abs(AbsoluteAxis::MultitouchPositionY, 100),
+    ];
+    expected.extend(finger_count(1));
+    expected.extend([abs(AbsoluteAxis::X, 900), abs(AbsoluteAxis::Y, 100)]);
+    assert_eq!(slots.frame("phone", &[contact(9, 0.9, 0.1)], 1000, 1000), expected);
+
+    let mut expected = vec![
+        abs(AbsoluteAxis::MultitouchSlot, 1),
+        abs(AbsoluteAxis::MultitouchTrackingId, -1),
+    ];
+    expected.extend(finger_count(0));
+    assert_eq!(slots.frame("phone", &[], 1000, 1000), expected);
+}
+
+#[test]
+fn extra_fingers_are_ignored() {
+    let mut slots = MultitouchSlots::default();
+    let contacts: Vec<_> = (0..7).map(|id| contact(id, 0.5, 0.5)).collect();
+    let events = slots.frame("phone", &contacts, 100, 100);
+    let new_tracks = events.iter()
+        .filter(|(_, code, value)| *code == AbsoluteAxis::MultitouchTrackingId as u16 && *value >= 0)
+        .count();
+    assert_eq!(new_tracks, 5);
+    assert!(events.contains(&key(Key::ButtonToolQuintTap, 1)));
+}
+
+#[test]
+fn sources_keep_their_own_fingers() {
+    let mut slots = MultitouchSlots::default();
+    slots.frame("phone", &[contact(0, 0.1, 0.1)], 100, 100);
+
+    // The same contact id from another phone is another finger
+    let events = slots.frame("tablet", &[contact(0, 0.9, 0.9)], 100, 100);
+    assert_eq!(events[..2], [abs(AbsoluteAxis::MultitouchSlot, 1), abs(AbsoluteAxis::MultitouchTrackingId, 1)]);
+    assert!(events.contains(&key(Key::ButtonToolDoubleTap, 1)));
+
+    // Lifting everything on one phone leaves the other's finger down
+    let mut expected = vec![
+        abs(AbsoluteAxis::MultitouchSlot, 1),
+        abs(AbsoluteAxis::MultitouchTrackingId, -1),
+    ];
+    expected.extend(finger_count(1));
+    expected.extend([abs(AbsoluteAxis::X, 10), abs(AbsoluteAxis::Y, 10)]);
+    assert_eq!(slots.frame("tablet", &[], 100, 100), expected);
+}
+
+fn monitor(id: i32, x: i32, y: i32, width: i32, height: i32) -> MonitorInfo {
+    MonitorInfo { id, name: format!("DP-{}", id), width, height, x, y, focused: false, scale: 1.0, transform: 0 }
+}
+
+#[test]
+fn monitor_areas_cover_their_part_of_the_desktop() {
+    // A 1080p laptop panel below and right of the left edge of a 1440p monitor
+    let monitors = [monitor(0, 0, 0, 2560, 1440), monitor(1, 640, 1440, 1920, 1080)];
+
+    let top = ScreenArea::of_monitor(&monitors, 0);
+    assert_eq!(top, ScreenArea { x: 0.0, y: 0.0, width: 1.0, height: 1440.0 / 2520.0 });
+    let bottom = ScreenArea::of_monitor(&monitors, 1);
+    assert_eq!(bottom.map(0.0, 0.0), (0.25, 1440.0 / 2520.0));
+    assert_eq!(bottom.map(1.0, 1.0), (1.0, 1.0));
+    // Out-of-range positions stay on the monitor
+    assert_eq!(bottom.map(-0.5, 2.0), (0.25, 1.0));
+
+    assert_eq!(ScreenArea::of_monitor(&monitors, 7), ScreenArea::WHOLE);
+    assert_eq!(ScreenArea::of_monitor(&[], 0), ScreenArea::WHOLE);
+}
+
+#[test]
+fn scaled_and_rotated_monitors_use_their_layout_size() {
+    // A 4K panel at scale 2 takes 1920x1080 of the layout, and a 1080p
+    // monitor rotated to portrait right of it 1080x1920
+    let laptop = MonitorInfo { scale: 2.0, ..monitor(0, 0, 0, 3840, 2160) };
+    let portrait = MonitorInfo { transform: 1, ..monitor(1, 1920, 0, 1920, 1080) };
+    let monitors = [laptop, portrait];
+
+    let left = ScreenArea::of_monitor(&monitors, 0);
+    assert_eq!(left, ScreenArea { x: 0.0, y: 0.0, width: 1920.0 / 3000.0, height: 1080.0 / 1920.0 });
+    let right = ScreenArea::of_monitor(&monitors, 1);
+    assert_eq!(right, ScreenArea { x: 1920.0 / 3000.0, y: 0.0, width: 1080.0 / 3000.0, height: 1.0 });
+    assert_eq!(right.map(1.0, 1.0), (1.0, 1.0));
+}
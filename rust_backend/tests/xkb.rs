@@ -0,0 +1,36 @@
+//! Resolving characters against a real XKB layout. Needs libxkbcommon and
+//! the xkeyboard-config data, and is skipped where they are missing.
+
+use wayland_connect_backend::xkb::{Keymap, Stroke};
+
+const GERMAN: &str = r#"xkb_keymap {
+    xkb_keycodes { include "evdev+aliases(qwertz)" };
+    xkb_types { include "complete" };
+    xkb_compat { include "complete" };
+    xkb_symbols { include "pc+de+inet(evdev)" };
+};"#;
+
+fn stroke(key: u16, shift: bool, level3: bool) -> Option<Stroke> {
+    Some(Stroke { key, shift, level3 })
+}
+
+#[test]
+fn resolves_characters_on_a_german_layout() {
+    let Some(mut keymap) = Keymap::from_string(GERMAN) else {
+        eprintln!("libxkbcommon or its layouts are missing, skipping");
+        return;
+    };
+
+    // QWERTZ swaps Y and Z
+    assert_eq!(keymap.resolve('z', 0), stroke(21, false, false));
+    assert_eq!(keymap.resolve('Y', 0), stroke(44, true, false));
+    assert_eq!(keymap.resolve('!', 0), stroke(2, true, false));
+    assert_eq!(keymap.resolve('@', 0), stroke(16, false, true));
+    assert_eq!(keymap.resolve('€', 0), stroke(18, false, true));
+    assert_eq!(keymap.resolve('ß', 0), stroke(12, false, false));
+    assert_eq!(keymap.resolve('\n', 0), stroke(28, false, false));
+
+    // Left to the compositor
+    assert_eq!(keymap.resolve('漢', 0), None);
+    assert_eq!(keymap.resolve('🙂', 0), None);
+}
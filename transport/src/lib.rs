@@ -1,4 +1,3 @@
 #![deny(warnings)]
-pub mod protocol;
 pub mod server;
 pub mod tcp;
@@ -1,25 +1,86 @@
 use anyhow::Result;
-use quinn::{Endpoint, ServerConfig};
-use tracing::{info, error};
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use quinn::{Endpoint, ServerConfig, TransportConfig};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tracing::{info, error, debug};
+use wc_core::error::WcError;
+use wc_core::traits::TransportPeer;
+use wc_protocol::framing;
+
+/// ALPN id negotiated by clients of the QUIC transport.
+pub const ALPN: &[u8] = b"wayland-connect";
+
+/// Channel carried on the reliable bidirectional stream. Every other
+/// channel id goes out as unreliable datagrams.
+pub const CONTROL_CHANNEL: u8 = 0;
+
+/// Time a peer gets to finish the QUIC handshake and open its control stream.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bound for a single control frame.
+const MAX_CONTROL_FRAME: usize = 10 * 1024 * 1024;
+
+/// Upper bound for the first control frame, which carries the peer's media
+/// token (32 hex characters) before anything about the peer is known.
+pub const MAX_PREAUTH_CONTROL_FRAME: usize = 256;
+
+/// Datagram header: channel (u8), message sequence (u32), fragment index
+/// (u16) and fragment count (u16), all big-endian.
+const DATAGRAM_HEADER: usize = 9;
 
 pub struct TransportServer {
     endpoint: Endpoint,
 }
 
 impl TransportServer {
-    pub async fn new(bind_addr: std::net::SocketAddr) -> Result<Self> {
-        let endpoint = make_server_endpoint(bind_addr)?;
+    /// Binds a QUIC endpoint using the same rustls configuration (server
+    /// certificate and client verifier) as the TLS listener.
+    pub fn new(bind_addr: std::net::SocketAddr, mut crypto: rustls::ServerConfig) -> Result<Self> {
+        crypto.alpn_protocols = vec![ALPN.to_vec()];
+
+        let mut transport = TransportConfig::default();
+        transport.keep_alive_interval(Some(Duration::from_secs(5)));
+
+        let mut server_config = ServerConfig::with_crypto(Arc::new(crypto));
+        server_config.transport_config(Arc::new(transport));
+
+        let endpoint = Endpoint::server(server_config, bind_addr)?;
         Ok(Self { endpoint })
     }
 
-    pub async fn run(&self) -> Result<()> {
+    /// Accepts connections and hands every peer that opened its control
+    /// stream to `peers`, along with what `admit` returned for its address.
+    /// Peers `admit` turns away are refused before the handshake; the
+    /// handshake and control stream have to happen within
+    /// `HANDSHAKE_TIMEOUT`.
+    pub async fn run<A: Send + 'static>(
+        &self,
+        peers: mpsc::Sender<(QuicPeer, A)>,
+        admit: impl Fn(std::net::SocketAddr) -> Option<A>,
+    ) -> Result<()> {
         info!("🚀 Transport Server listening on {}", self.endpoint.local_addr()?);
 
         while let Some(incoming) = self.endpoint.accept().await {
-            info!("📡 Incoming handshake...");
+            let remote = incoming.remote_address();
+            let Some(admission) = admit(remote) else {
+                // Dropping `Connecting` refuses the connection
+                continue;
+            };
+            info!("📡 Incoming handshake from {}...", remote);
+            let peers = peers.clone();
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(incoming).await {
-                    error!("Connection error: {}", e);
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, handle_connection(incoming)).await {
+                    Ok(Ok(peer)) => {
+                        let _ = peers.send((peer, admission)).await;
+                    }
+                    Ok(Err(e)) => error!("Connection error: {}", e),
+                    Err(_) => error!("Handshake with {} timed out", remote),
                 }
             });
         }
@@ -28,28 +89,216 @@ impl TransportServer {
     }
 }
 
-async fn handle_connection(connecting: quinn::Connecting) -> Result<()> {
+async fn handle_connection(connecting: quinn::Connecting) -> Result<QuicPeer> {
     // quinn 0.10: Connecting is a Future that yields Result<Connection>
     let connection = connecting.await?;
     info!("✅ Connected: {}", connection.remote_address());
-    
-    // Placeholder: accept a stream
-    // while let Ok((_send, _recv)) = connection.accept_bi().await {
-    // }
 
-    Ok(())
+    // The client opens the control stream right after the handshake
+    let (send, recv) = connection.accept_bi().await?;
+    Ok(QuicPeer::new(connection, send, recv))
+}
+
+/// A QUIC connection seen through `TransportPeer`.
+///
+/// Channel 0 is a length-prefixed stream of frames on one bidirectional
+/// stream, so control messages keep their order and never get lost. Media
+/// channels use datagrams, fragmented to the path MTU: a lost fragment drops
+/// that one message instead of stalling everything queued behind it.
+pub struct QuicPeer {
+    connection: quinn::Connection,
+    control_send: quinn::SendStream,
+    control_rx: mpsc::Receiver<Bytes>,
+    media: MediaSender,
+    reassembly: HashMap<u8, Reassembly>,
 }
 
-fn make_server_endpoint(bind_addr: std::net::SocketAddr) -> Result<Endpoint> {
-    let (cert, key) = simple_cert()?;
-    let server_config = ServerConfig::with_single_cert(vec![cert], key)?;
-    let endpoint = Endpoint::server(server_config, bind_addr)?;
-    Ok(endpoint)
+impl QuicPeer {
+    fn new(connection: quinn::Connection, control_send: quinn::SendStream, control_recv: quinn::RecvStream) -> Self {
+        // Reading a frame is not cancel-safe, so the control stream gets its
+        // own task and `receive` only selects over channels.
+        let (control_tx, control_rx) = mpsc::channel(64);
+        tokio::spawn(read_control_frames(control_recv, control_tx));
+
+        Self {
+            media: MediaSender { connection: connection.clone(), seq: Arc::new(AtomicU32::new(0)) },
+            connection,
+            control_send,
+            control_rx,
+            reassembly: HashMap::new(),
+        }
+    }
+
+    pub fn remote_address(&self) -> std::net::SocketAddr {
+        self.connection.remote_address()
+    }
+
+    /// Handle for sending datagrams from other tasks while this peer keeps
+    /// serving the control stream.
+    pub fn media_sender(&self) -> MediaSender {
+        self.media.clone()
+    }
+
+    pub fn close(&self) {
+        self.connection.close(0u32.into(), b"closed");
+    }
+
+    fn accept_fragment(&mut self, datagram: Bytes) -> Option<(u8, Bytes)> {
+        if datagram.len() < DATAGRAM_HEADER {
+            return None;
+        }
+        let channel = datagram[0];
+        let seq = u32::from_be_bytes([datagram[1], datagram[2], datagram[3], datagram[4]]);
+        let index = u16::from_be_bytes([datagram[5], datagram[6]]) as usize;
+        let count = u16::from_be_bytes([datagram[7], datagram[8]]) as usize;
+        let payload = datagram.slice(DATAGRAM_HEADER..);
+        if index >= count {
+            return None;
+        }
+        if count == 1 {
+            return Some((channel, payload));
+        }
+
+        let current = self.reassembly.entry(channel).or_insert_with(|| Reassembly::new(seq, count));
+        if current.seq != seq {
+            // Only a newer message replaces an incomplete one
+            if (seq.wrapping_sub(current.seq) as i32) < 0 {
+                return None;
+            }
+            *current = Reassembly::new(seq, count);
+        }
+        current.insert(index, payload)?;
+        let message = current.take();
+        self.reassembly.remove(&channel);
+        Some((channel, message))
+    }
 }
 
-fn simple_cert() -> Result<(rustls::Certificate, rustls::PrivateKey)> {
-    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()])?;
-    let key = rustls::PrivateKey(cert.serialize_private_key_der());
-    let cert = rustls::Certificate(cert.serialize_der()?);
-    Ok((cert, key))
+#[async_trait]
+impl TransportPeer for QuicPeer {
+    async fn send(&mut self, channel_id: u8, data: &[u8]) -> wc_core::error::Result<()> {
+        if channel_id != CONTROL_CHANNEL {
+            return self.media.send(channel_id, data);
+        }
+        self.control_send.write_all(&(data.len() as u32).to_be_bytes()).await
+            .map_err(|e| WcError::Transport(e.to_string()))?;
+        self.control_send.write_all(data).await
+            .map_err(|e| WcError::Transport(e.to_string()))
+    }
+
+    async fn receive(&mut self) -> wc_core::error::Result<(u8, Bytes)> {
+        loop {
+            tokio::select! {
+                frame = self.control_rx.recv() => {
+                    return match frame {
+                        Some(frame) => Ok((CONTROL_CHANNEL, frame)),
+                        None => Err(WcError::Transport("control stream closed".to_string())),
+                    };
+                }
+                datagram = self.connection.read_datagram() => {
+                    let datagram = datagram.map_err(|e| WcError::Transport(e.to_string()))?;
+                    if let Some(message) = self.accept_fragment(datagram) {
+                        return Ok(message);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Forwards length-prefixed control frames from `recv` to `tx` until the
+/// stream ends or breaks the framing. The first frame is the peer's
+/// credential and may be at most `MAX_PREAUTH_CONTROL_FRAME` bytes; later
+/// ones up to `MAX_CONTROL_FRAME`.
+pub async fn read_control_frames<R: AsyncRead + Unpin>(mut recv: R, tx: mpsc::Sender<Bytes>) {
+    let mut limit = MAX_PREAUTH_CONTROL_FRAME;
+    loop {
+        let frame = match framing::read_frame_limited(&mut recv, limit).await {
+            Ok(frame) => frame,
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::InvalidData {
+                    error!("Control frame rejected: {}", e);
+                }
+                break;
+            }
+        };
+        if tx.send(Bytes::from(frame)).await.is_err() {
+            break;
+        }
+        limit = MAX_CONTROL_FRAME;
+    }
+    debug!("Control stream reader finished");
+}
+
+/// Cloneable datagram side of a `QuicPeer`.
+#[derive(Clone)]
+pub struct MediaSender {
+    connection: quinn::Connection,
+    seq: Arc<AtomicU32>,
+}
+
+impl MediaSender {
+    /// Sends `data` on `channel_id` as one or more datagrams. Nothing is
+    /// retransmitted; if the send buffer is full quinn drops the oldest.
+    pub fn send(&self, channel_id: u8, data: &[u8]) -> wc_core::error::Result<()> {
+        let max = self.connection.max_datagram_size()
+            .ok_or_else(|| WcError::Transport("peer does not accept datagrams".to_string()))?;
+        let chunk = max.saturating_sub(DATAGRAM_HEADER).max(1);
+        let count = data.len().div_ceil(chunk).max(1);
+        if count > u16::MAX as usize {
+            return Err(WcError::Transport(format!("message of {} bytes is too large", data.len())));
+        }
+
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        for index in 0..count {
+            let part = &data[(index * chunk).min(data.len())..((index + 1) * chunk).min(data.len())];
+            let mut datagram = BytesMut::with_capacity(DATAGRAM_HEADER + part.len());
+            datagram.put_u8(channel_id);
+            datagram.put_u32(seq);
+            datagram.put_u16(index as u16);
+            datagram.put_u16(count as u16);
+            datagram.put_slice(part);
+            self.connection.send_datagram(datagram.freeze())
+                .map_err(|e| WcError::Transport(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        self.connection.close(0u32.into(), b"closed");
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.connection.close_reason().is_some()
+    }
+}
+
+struct Reassembly {
+    seq: u32,
+    parts: Vec<Option<Bytes>>,
+    received: usize,
+}
+
+impl Reassembly {
+    fn new(seq: u32, count: usize) -> Self {
+        Self { seq, parts: vec![None; count], received: 0 }
+    }
+
+    /// Stores a fragment; returns `Some` once every fragment is present.
+    fn insert(&mut self, index: usize, payload: Bytes) -> Option<()> {
+        let slot = self.parts.get_mut(index)?;
+        if slot.is_none() {
+            *slot = Some(payload);
+            self.received += 1;
+        }
+        (self.received == self.parts.len()).then_some(())
+    }
+
+    fn take(&mut self) -> Bytes {
+        let mut message = BytesMut::new();
+        for part in self.parts.iter_mut().filter_map(Option::take) {
+            message.extend_from_slice(&part);
+        }
+        message.freeze()
+    }
 }
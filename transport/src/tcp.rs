@@ -1,19 +1,20 @@
 use anyhow::Result;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::sync::{mpsc, broadcast};
 use tracing::{info, error};
-use crate::protocol::Packet;
+use wc_protocol::{framing, ClientMessage, InputEvent};
 use std::net::SocketAddr;
 
 pub struct TcpServer {
     bind_addr: SocketAddr,
-    event_tx: mpsc::Sender<Packet>,
-    broadcast_tx: broadcast::Sender<Packet>,
+    event_tx: mpsc::Sender<InputEvent>,
+    /// Already framed messages (see `wc_protocol::framing::encode`)
+    broadcast_tx: broadcast::Sender<Vec<u8>>,
 }
 
 impl TcpServer {
-    pub fn new(bind_addr: SocketAddr, event_tx: mpsc::Sender<Packet>, broadcast_tx: broadcast::Sender<Packet>) -> Self {
+    pub fn new(bind_addr: SocketAddr, event_tx: mpsc::Sender<InputEvent>, broadcast_tx: broadcast::Sender<Vec<u8>>) -> Self {
         Self { bind_addr, event_tx, broadcast_tx }
     }
 
@@ -29,7 +30,8 @@ impl TcpServer {
             let broadcast_rx = self.broadcast_tx.subscribe();
             
             tokio::spawn(async move {
-                if let Err(e) = handle_socket(socket, event_tx, broadcast_rx).await {
+                let (reader, writer) = socket.into_split();
+                if let Err(e) = handle_connection(reader, writer, event_tx, broadcast_rx).await {
                     error!("TCP Error {}: {}", addr, e);
                 }
             });
@@ -37,40 +39,57 @@ impl TcpServer {
     }
 }
 
-async fn handle_socket(
-    mut socket: TcpStream, 
-    event_tx: mpsc::Sender<Packet>, 
-    mut broadcast_rx: broadcast::Receiver<Packet>
-) -> Result<()> {
-    let mut len_buf = [0u8; 4];
+/// Serves one client: its events go to `event_tx`, broadcasts go back to it.
+/// Nothing on this transport authenticates the peer, so every frame is held
+/// to `framing::MAX_PREAUTH_FRAME_LEN`.
+pub async fn handle_connection<R, W>(
+    mut reader: R,
+    mut writer: W,
+    event_tx: mpsc::Sender<InputEvent>,
+    mut broadcast_rx: broadcast::Receiver<Vec<u8>>
+) -> Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin,
+{
+    // Reading a frame isn't cancel-safe, so it gets its own task
+    let (frame_tx, mut frame_rx) = mpsc::channel::<Vec<u8>>(64);
+    let read_task = tokio::spawn(async move {
+        while let Ok(body) = framing::read_frame_limited(&mut reader, framing::MAX_PREAUTH_FRAME_LEN).await {
+            if frame_tx.send(body).await.is_err() { break; }
+        }
+    });
     
+    let result = forward(&mut frame_rx, &mut writer, &event_tx, &mut broadcast_rx).await;
+    // Also on errors, or the reader stays blocked on the socket
+    read_task.abort();
+    info!("🔌 TCP Client Disconnected");
+    result
+}
+
+async fn forward<W: AsyncWrite + Unpin>(
+    frame_rx: &mut mpsc::Receiver<Vec<u8>>,
+    writer: &mut W,
+    event_tx: &mpsc::Sender<InputEvent>,
+    broadcast_rx: &mut broadcast::Receiver<Vec<u8>>,
+) -> Result<()> {
     loop {
         tokio::select! {
             // Read from Client
-            read_res = socket.read_exact(&mut len_buf) => {
-                read_res?;
-                let len = u32::from_le_bytes(len_buf) as usize;
-                let mut buf = vec![0u8; len];
-                socket.read_exact(&mut buf).await?;
-                
-                let packet: Packet = rmp_serde::from_slice(&buf)?;
-                if let Err(e) = event_tx.send(packet).await {
+            body = frame_rx.recv() => {
+                let Some(body) = body else { break };
+                let message: ClientMessage = framing::decode(&body)?;
+                if let Err(e) = event_tx.send(message.event).await {
                     error!("Failed to forward packet: {}", e);
                     break;
                 }
             }
             // Write to Client (Broadcast from Daemon)
             broadcast_res = broadcast_rx.recv() => {
-                let packet = broadcast_res?;
-                let payload = rmp_serde::to_vec(&packet)?;
-                let len = payload.len() as u32;
-                
-                socket.write_all(&len.to_le_bytes()).await?;
-                socket.write_all(&payload).await?;
+                let frame = broadcast_res?;
+                writer.write_all(&frame).await?;
             }
         }
     }
-    
-    info!("🔌 TCP Client Disconnected");
     Ok(())
 }
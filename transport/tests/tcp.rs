@@ -0,0 +1,21 @@
+//! The plain TCP transport's connection handling.
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, mpsc};
+use wc_transport::tcp::handle_connection;
+
+#[tokio::test]
+async fn undecodable_frame_stops_the_reader_too() {
+    let (mut client, server) = tokio::io::duplex(1024);
+    let (event_tx, _event_rx) = mpsc::channel(1);
+    let (_broadcast_tx, broadcast_rx) = broadcast::channel(1);
+
+    // A frame whose body is no message
+    client.write_all(&[0, 0, 0, 1, 0xc1]).await.unwrap();
+    assert!(handle_connection(server, tokio::io::sink(), event_tx, broadcast_rx).await.is_err());
+
+    // With the reader gone nothing holds the server's end any more
+    let closed = tokio::time::timeout(Duration::from_secs(1), client.read(&mut [0u8; 1])).await;
+    assert!(matches!(closed, Ok(Ok(0))), "the reader task still holds the stream");
+}
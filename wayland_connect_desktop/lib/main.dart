@@ -11,7 +11,6 @@ import 'package:screen_retriever/screen_retriever.dart';
 import 'dart:async';
 import './utils/protocol.dart';
 import 'l10n/app_localizations.dart';
-import 'package:crypto/crypto.dart';
 
 // ignore: must_be_immutable
 class _SidebarItem extends StatelessWidget {
@@ -527,37 +526,27 @@ X-GNOME-Autostart-enabled=true
   void _connectToBackend() async {
     _socket?.close();
     
-    int port = int.tryParse(_portController.text) ?? 12345;
+    // Management goes over the local control socket; the TLS port is for phones
+    final runtimeDir = Platform.environment['XDG_RUNTIME_DIR'];
+    if (runtimeDir == null) {
+      debugPrint("⚠️ XDG_RUNTIME_DIR is not set; cannot reach the backend control socket");
+      return;
+    }
     
     try {
-      final prefs = await SharedPreferences.getInstance();
-      final expectedFingerprint = prefs.getString('backend_fingerprint');
-
-      _socket = await SecureSocket.connect(
-        '127.0.0.1', 
-        port,
-        onBadCertificate: (certificate) {
-          final hash = sha256.convert(certificate.der).toString().toUpperCase();
-          final formattedHash = hash.replaceAllMapped(RegExp(r".{2}"), (match) => "${match.group(0)}:").substring(0, hash.length + (hash.length / 2).floor() - 1);
-          
-          if (expectedFingerprint != null && expectedFingerprint.isNotEmpty) {
-             if (expectedFingerprint == formattedHash) {
-                return true;
-             } else {
-                debugPrint("🚨 BACKEND IDENTITY CHANGED! Expected: $expectedFingerprint, Got: $formattedHash");
-                return false;
-             }
-          }
-          // First time, pin it
-          prefs.setString('backend_fingerprint', formattedHash);
-          debugPrint("📌 Pinned Local Backend Fingerprint: $formattedHash");
-          return true;
-        },
+      _socket = await Socket.connect(
+        InternetAddress('$runtimeDir/wayland-connect.sock', type: InternetAddressType.unix),
+        0,
       );
       if (mounted) setState(() => _isConnected = true);
       
-      // Mark this connection as a dashboard so it receives broadcasts
-      _socket!.add(ProtocolHandler.encodePacket({'type': 'register_dashboard'}));
+      // Mark this connection as a dashboard so it receives broadcasts.
+      // The backend only accepts it with the token from its config dir.
+      final token = await _readDashboardToken();
+      _socket!.add(ProtocolHandler.encodePacket({
+        'type': 'register_dashboard',
+        'data': {'token': token ?? ''},
+      }));
       
       _socket!.listen(
         (data) {
@@ -742,6 +731,17 @@ X-GNOME-Autostart-enabled=true
     }
   }
 
+  Future<String?> _readDashboardToken() async {
+    final configHome = Platform.environment['XDG_CONFIG_HOME'] ?? '${Platform.environment['HOME']}/.config';
+    final file = File('$configHome/wayland-connect/dashboard.token');
+    try {
+      return (await file.readAsString()).trim();
+    } catch (e) {
+      debugPrint("⚠️ Could not read dashboard token: $e");
+      return null;
+    }
+  }
+
   void _approveDevice(String id) {
     if (_socket != null) {
       final event = {